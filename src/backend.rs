@@ -0,0 +1,129 @@
+//! What a search has to provide to be driven by a protocol adapter, `uci` or `xboard`.
+
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::{ChessBoard, Move, MoveOutcome, PieceKind};
+
+/// Limits for a search as given by a GUI. Fields left as `None` don't limit the search.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct SearchLimits {
+    /// Depth in half moves.
+    pub depth: Option<u32>,
+    /// Number of positions to look at.
+    pub nodes: Option<u64>,
+    /// Exact time to search for.
+    pub movetime: Option<Duration>,
+    /// Time left on the clocks, white first.
+    pub time: [Option<Duration>; 2],
+    /// Time added to the clocks after each move, white first.
+    pub increment: [Duration; 2],
+    /// Moves until the next time control, or `None` if the rest of the game must be played in the time left.
+    pub moves_to_go: Option<u32>,
+    /// Search until told to stop.
+    pub infinite: bool
+}
+
+/// Numeric option a search supports, e.g. the hash size.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct EngineOption {
+    pub name: String,
+    pub default: i64,
+    pub min: i64,
+    pub max: i64
+}
+
+/// Search that a protocol adapter can drive.
+pub trait EngineBackend {
+    /// Get the name the engine reports to the GUI.
+    fn name(&self) -> String;
+
+    /// Get the options the engine supports.
+    fn options(&self) -> Vec<EngineOption> { return vec![]; }
+
+    /// Set an option by name. Unknown options and invalid values are ignored.
+    fn set_option(&mut self, _name: &str, _value: &str) { }
+
+    /// Forget what was learned in the previous game, e.g. cached positions.
+    fn new_game(&mut self) { }
+
+    /**
+    Search for the best move.                                                   <br/>
+    Parameters:                                                                 <br/>
+    `board`: Position to search                                                 <br/>
+    `limits`: When to stop searching                                            <br/>
+    `stop`: Set by the adapter when the GUI wants a move right away             <br/>
+    Returns:                                                                    <br/>
+    The best move found, or `None` if there are no legal moves.
+    */
+    fn search(&mut self, board: &ChessBoard, limits: &SearchLimits, stop: &AtomicBool) -> Option<Move>;
+}
+
+/// Add a queen promotion if the backend left it out, as the protocols require the piece.
+pub(crate) fn with_promotion(board: &ChessBoard, m: Move) -> Move {
+    let last_rank = m.to.rank().index() == 0 || m.to.rank().index() == 7;
+    if m.promotion.is_none() && last_rank && board.piece_at(m.from).is_some_and(|p| p.0 == PieceKind::Pawn) {
+        return Move { promotion: Some(PieceKind::Queen), ..m };
+    }
+
+    return m;
+}
+
+/// Set up a board from the fields of a FEN string, split on whitespace like the protocols send them.
+pub(crate) fn parse_fen(fields: &[&str]) -> Option<ChessBoard> {
+    return ChessBoard::from_fen(&fields.join(" ")).ok();
+}
+
+/// Play a move in long algebraic notation like "e2e4" or "e7e8q". Returns `false` if it isn't legal, which includes a promotion without the piece.
+pub(crate) fn play_long_algebraic(board: &mut ChessBoard, m: &str) -> bool {
+    if m.len() < 4 || !m.is_char_boundary(2) { return false; }
+
+    return match board.move_by_algebraic(&m[..2], &m[2..]) {
+        Ok(MoveOutcome::Played(_)) => { true }
+        Ok(MoveOutcome::PromotionRequired) => {
+            // The protocols always name the piece, so don't leave the board waiting for one.
+            board.undo();
+            false
+        }
+        Err(_) => { false }
+    };
+}
+
+/// Backend and output used to test the protocol adapters.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::io::{self, Write};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+
+    use super::{EngineBackend, SearchLimits};
+    use crate::{ChessBoard, Move};
+
+    /// Backend playing the first legal move.
+    pub(crate) struct First;
+
+    impl EngineBackend for First {
+        fn name(&self) -> String { return "First".to_string(); }
+
+        fn search(&mut self, board: &ChessBoard, _limits: &SearchLimits, _stop: &AtomicBool) -> Option<Move> {
+            return board.legal_moves().next();
+        }
+    }
+
+    /// Output shared with the test after `run()` takes it.
+    #[derive(Clone, Default)]
+    pub(crate) struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Shared {
+        /// Get the lines written so far.
+        pub(crate) fn lines(&self) -> Vec<String> {
+            let text = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            return text.lines().map(str::to_string).collect();
+        }
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> { return self.0.lock().unwrap().write(buf); }
+        fn flush(&mut self) -> io::Result<()> { return Ok(()); }
+    }
+}
@@ -0,0 +1,155 @@
+//! Chess clock with Fischer increment or Bronstein delay.
+
+use std::time::{Duration, Instant};
+
+use crate::Color;
+
+/// Time given back to a player after each move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum Bonus {
+    #[default]
+    None,
+    /// Added after every move, so the clock can gain time.
+    Fischer(Duration),
+    /// The time a move took is given back up to this much, so the clock only runs down on slower moves.
+    Bronstein(Duration)
+}
+
+/**
+Clocks of both players, of which at most one runs at a time. Pressing it after a move stops    <br/>
+the mover's clock, adds the bonus and starts the opponent's. When a running clock reaches      <br/>
+zero, that player's flag falls and both clocks stop at the next press.
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Clock {
+    /// Time left when the clocks were last pressed or stopped, white first.
+    remaining: [Duration; 2],
+    bonus: Bonus,
+    /// Player whose clock runs and since when.
+    running: Option<(Color, Instant)>,
+    /// Player whose flag fell.
+    flag: Option<Color>
+}
+
+impl Clock {
+    /**
+    Get a clock with the same time for both players. Neither clock runs yet.     <br/>
+    Parameters:                                                                  <br/>
+    `time`: Time for each player                                                 <br/>
+    `bonus`: Time given back after each move                                     <br/>
+    Returns:                                                                     <br/>
+    The clock.
+    */
+    pub fn new(time: Duration, bonus: Bonus) -> Clock {
+        return Clock { remaining: [time; 2], bonus, running: None, flag: None };
+    }
+
+    /// Get the time left on a player's clock, counting down while it runs. Zero once the flag fell.
+    pub fn time_remaining(&self, color: Color) -> Duration { return self.remaining_at(color, Instant::now()); }
+
+    /// Get the player whose clock runs, if any.
+    pub fn running(&self) -> Option<Color> { return self.running.map(|r| r.0); }
+
+    /**
+    Check for a flag fall.                                           <br/>
+    Returns:                                                         <br/>
+    The player who ran out of time, or `None` while both have time left
+    */
+    pub fn flag(&self) -> Option<Color> { return self.flag_at(Instant::now()); }
+
+    /// Start a player's clock, stopping the other one without a bonus, e.g. at the start of a game.
+    pub fn start(&mut self, color: Color) { self.start_at(color, Instant::now()); }
+
+    /// Stop both clocks without a bonus, e.g. when the game has ended or is paused.
+    pub fn stop(&mut self) { self.stop_at(Instant::now()); }
+
+    /// Stop the clock of a player who moved, give them the bonus and start the opponent's clock. After a flag fall it only stops the clocks.
+    pub fn press(&mut self, color: Color) { self.press_at(color, Instant::now()); }
+
+    fn remaining_at(&self, color: Color, now: Instant) -> Duration {
+        let left = self.remaining[color as usize];
+        return match self.running {
+            Some((c, since)) if c == color => { left.saturating_sub(now.saturating_duration_since(since)) }
+            _ => { left }
+        };
+    }
+
+    fn flag_at(&self, now: Instant) -> Option<Color> {
+        return self.flag.or(self.running.map(|r| r.0).filter(|c| self.remaining_at(*c, now).is_zero()));
+    }
+
+    fn start_at(&mut self, color: Color, now: Instant) {
+        self.stop_at(now);
+        if self.flag.is_none() { self.running = Some((color, now)); }
+    }
+
+    fn stop_at(&mut self, now: Instant) {
+        self.flag = self.flag_at(now);
+        if let Some((c, _)) = self.running { self.remaining[c as usize] = self.remaining_at(c, now); }
+        self.running = None;
+    }
+
+    fn press_at(&mut self, color: Color, now: Instant) {
+        let used = self.running.filter(|r| r.0 == color).map(|r| now.saturating_duration_since(r.1));
+        self.stop_at(now);
+        if self.flag.is_some() { return; }
+
+        // The first move is played before any clock runs, so it gets no bonus.
+        if let Some(used) = used {
+            self.remaining[color as usize] += match self.bonus {
+                Bonus::None => { Duration::ZERO }
+                Bonus::Fischer(increment) => { increment }
+                Bonus::Bronstein(delay) => { used.min(delay) }
+            };
+        }
+
+        self.running = Some((color.opposite(), now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn increment_and_delay() {
+        let start = Instant::now();
+        let at = |s: u64| start + SECOND * s as u32;
+
+        // The first move is free, then white thinks 10 s and black 3 s.
+        let mut clock = Clock::new(SECOND * 60, Bonus::Fischer(SECOND * 2));
+        clock.press_at(Color::White, at(0));
+        assert_eq!(clock.running(), Some(Color::Black));
+        clock.press_at(Color::Black, at(3));
+        assert_eq!(clock.remaining_at(Color::White, at(13)), SECOND * 50);
+        clock.press_at(Color::White, at(13));
+        assert_eq!((clock.remaining_at(Color::White, at(14)), clock.remaining_at(Color::Black, at(14))), (SECOND * 52, SECOND * 58));
+
+        // A delay only gives back the time used, up to the delay.
+        let mut clock = Clock::new(SECOND * 60, Bonus::Bronstein(SECOND * 5));
+        clock.start_at(Color::White, at(0));
+        clock.press_at(Color::White, at(3));
+        clock.press_at(Color::Black, at(11));
+        assert_eq!((clock.remaining[0], clock.remaining[1]), (SECOND * 60, SECOND * 57));
+
+        clock.stop_at(at(20));
+        assert_eq!((clock.running(), clock.remaining[0]), (None, SECOND * 51));
+    }
+
+    #[test]
+    fn flag_fall() {
+        let start = Instant::now();
+        let mut clock = Clock::new(SECOND * 5, Bonus::Fischer(SECOND));
+        clock.start_at(Color::White, start);
+        assert_eq!(clock.flag_at(start + SECOND * 4), None);
+        assert_eq!(clock.flag_at(start + SECOND * 5), Some(Color::White));
+
+        // Moving after the flag fell stops the clocks without a bonus, and the flag stays down.
+        clock.press_at(Color::White, start + SECOND * 7);
+        assert_eq!((clock.running(), clock.flag(), clock.time_remaining(Color::White)), (None, Some(Color::White), Duration::ZERO));
+        clock.start_at(Color::Black, start + SECOND * 8);
+        assert_eq!((clock.running(), clock.time_remaining(Color::Black)), (None, SECOND * 5));
+    }
+}
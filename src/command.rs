@@ -0,0 +1,205 @@
+//! Forgiving move input for voice and chat interfaces, e.g. "knight f3" or "castle kingside".
+
+use std::fmt;
+
+use crate::{ChessBoard, File, Move, MoveKind, PieceKind, Rank, Square};
+
+/// Why a command couldn't be turned into a move.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum CommandError {
+    /// The command doesn't name a move, e.g. it has no destination square.
+    Unrecognized,
+    /// No legal move matches the command.
+    NoLegalMove,
+    /// Several legal moves match the command. Holds the candidates.
+    Ambiguous(Vec<Move>)
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            CommandError::Unrecognized => { write!(f, "couldn't understand the move") }
+            CommandError::NoLegalMove => { write!(f, "no legal move matches") }
+            CommandError::Ambiguous(moves) => {
+                let moves: Vec<String> = moves.iter().map(|m| format!("{}-{}", m.from, m.to)).collect();
+                write!(f, "the move is ambiguous: {}", moves.join(", "))
+            }
+        };
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// What a command asks for. Fields left as `None` match anything.
+#[derive(Default)]
+struct Query {
+    kind: Option<PieceKind>,
+    from: Option<Square>,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Option<Square>,
+    capture: bool,
+    castle: Option<MoveKind>
+}
+
+/**
+Find the legal move a command refers to in the current position.            <br/>
+Parameters:                                                                 <br/>
+`board`: Position to play the move in                                       <br/>
+`input`: Command like "knight f3", "pawn takes d5", "e2 to e4", "rook a d1" or "castle queenside"   <br/>
+Returns:                                                                    <br/>
+The move, or why no single legal move matches.
+*/
+pub fn parse_move(board: &ChessBoard, input: &str) -> Result<Move, CommandError> {
+    let query = parse_query(input).ok_or(CommandError::Unrecognized)?;
+    let pieces = board.get_board();
+
+    let moves: Vec<Move> = board.legal_moves().filter(|m| {
+        if let Some(c) = query.castle { return m.kind == c; }
+
+        let kind = pieces[m.from.index()].map(|p| p.0);
+        return query.kind.is_none_or(|k| kind == Some(k))
+            && query.from.is_none_or(|s| m.from == s)
+            && query.from_file.is_none_or(|f| m.from.file() == f)
+            && query.from_rank.is_none_or(|r| m.from.rank() == r)
+            && query.to.is_none_or(|s| m.to == s)
+            && (!query.capture || matches!(m.kind, MoveKind::Capture | MoveKind::EnPassant));
+    }).collect();
+
+    return match moves.len() {
+        0 => { Err(CommandError::NoLegalMove) }
+        1 => { Ok(moves[0]) }
+        _ => { Err(CommandError::Ambiguous(moves)) }
+    };
+}
+
+/// Turn a command into a query, or `None` if it doesn't name a move.
+fn parse_query(input: &str) -> Option<Query> {
+    let lower = input.to_lowercase();
+    let words: Vec<&str> = lower.split(|c: char| c.is_whitespace() || c == ',' || c == '.').filter(|w| !w.is_empty()).collect();
+    let mut query = Query::default();
+    let mut squares: Vec<Square> = vec![];
+    let mut castle = false;
+    let mut i = 0;
+
+    while i < words.len() {
+        let w = words[i];
+        i += 1;
+
+        // Speech recognition often splits squares, e.g. "f 3".
+        if w.len() == 1 && i < words.len() {
+            if let Ok(s) = format!("{}{}", w, words[i]).parse::<Square>() {
+                squares.push(s);
+                i += 1;
+                continue;
+            }
+        }
+
+        if let Ok(s) = w.parse::<Square>() { squares.push(s); continue; }
+
+        match w {
+            "o-o" | "0-0" => { query.castle = Some(MoveKind::KingsideCastle); }
+            "o-o-o" | "0-0-0" => { query.castle = Some(MoveKind::QueensideCastle); }
+            "castle" | "castles" | "castling" => { castle = true; }
+            "kingside" | "short" => { query.castle = Some(MoveKind::KingsideCastle); }
+            "queenside" | "long" => { query.castle = Some(MoveKind::QueensideCastle); }
+            "side" if castle => {
+                // "castle king side", the piece word was read as a king move.
+                query.castle = if query.kind == Some(PieceKind::Queen) { Some(MoveKind::QueensideCastle) } else { Some(MoveKind::KingsideCastle) };
+            }
+            "takes" | "take" | "captures" | "capture" | "x" => { query.capture = true; }
+            _ => {
+                if let Some(k) = piece_kind(w) {
+                    // Only the first piece named moves, later ones are what it captures.
+                    if query.kind.is_none() && squares.is_empty() { query.kind = Some(k); }
+                } else if let Some(f) = single(w, b'a').and_then(File::new) {
+                    query.from_file = Some(f);
+                } else if let Some(r) = single(w, b'1').and_then(Rank::new) {
+                    query.from_rank = Some(r);
+                }
+            }
+        }
+    }
+
+    if query.castle.is_some() {
+        return Some(query);
+    }
+
+    match squares.len() {
+        1 => {
+            // A lone square like "e4" is a pawn move.
+            if query.kind.is_none() && query.from_file.is_none() && query.from_rank.is_none() { query.kind = Some(PieceKind::Pawn); }
+            query.to = Some(squares[0]);
+        }
+        2 => {
+            query.from = Some(squares[0]);
+            query.to = Some(squares[1]);
+        }
+        _ => { return None; }
+    }
+
+    return Some(query);
+}
+
+/// Get the piece named by a word, including some common mishearings.
+fn piece_kind(word: &str) -> Option<PieceKind> {
+    return match word {
+        "pawn" | "pawns" | "p" => { Some(PieceKind::Pawn) }
+        "knight" | "night" | "horse" | "n" => { Some(PieceKind::Knight) }
+        "bishop" => { Some(PieceKind::Bishop) }
+        "rook" | "tower" | "r" => { Some(PieceKind::Rook) }
+        "queen" | "q" => { Some(PieceKind::Queen) }
+        "king" | "k" => { Some(PieceKind::King) }
+        _ => { None }
+    };
+}
+
+/// Get the offset of a single character word from `base`, e.g. 3 for "d" from 'a'.
+fn single(word: &str, base: u8) -> Option<usize> {
+    let b = word.as_bytes();
+    if b.len() != 1 || b[0] < base { return None; }
+
+    return Some((b[0] - base) as usize);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(s: &str) -> Square { return s.parse().unwrap(); }
+
+    #[test]
+    fn simple_moves() {
+        let mut board = ChessBoard::new();
+
+        assert_eq!(parse_move(&board, "e4").map(|m| (m.from, m.to)), Ok((sq("e2"), sq("e4"))));
+        assert_eq!(parse_move(&board, "Knight F3").map(|m| (m.from, m.to)), Ok((sq("g1"), sq("f3"))));
+        assert_eq!(parse_move(&board, "g 1 to f 3").map(|m| (m.from, m.to)), Ok((sq("g1"), sq("f3"))));
+        assert_eq!(parse_move(&board, "bishop e4"), Err(CommandError::NoLegalMove));
+        assert_eq!(parse_move(&board, "hello"), Err(CommandError::Unrecognized));
+
+        for (f, t) in [("e2", "e4"), ("d7", "d5")] { board.move_by_algebraic(f, t).unwrap(); }
+        assert_eq!(parse_move(&board, "pawn takes d5").map(|m| (m.from, m.kind)), Ok((sq("e4"), MoveKind::Capture)));
+        assert_eq!(parse_move(&board, "pawn takes pawn on d5").map(|m| m.to), Ok(sq("d5")));
+    }
+
+    #[test]
+    fn ambiguity_and_castling() {
+        let mut board = ChessBoard::new();
+        for (f, t) in [("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6"), ("b1", "c3"), ("f8", "c5"), ("d2", "d3"), ("d7", "d6")] {
+            board.move_by_algebraic(f, t).unwrap();
+        }
+
+        assert_eq!(parse_move(&board, "knight d2").map(|m| m.from), Ok(sq("f3")));
+        assert_eq!(parse_move(&board, "castle kingside").map(|m| m.kind), Ok(MoveKind::KingsideCastle));
+        assert_eq!(parse_move(&board, "castle king side").map(|m| m.kind), Ok(MoveKind::KingsideCastle));
+        assert_eq!(parse_move(&board, "O-O").map(|m| m.to), Ok(sq("g1")));
+        assert_eq!(parse_move(&board, "castle queenside"), Err(CommandError::NoLegalMove));
+
+        let mut board = ChessBoard::new();
+        for (f, t) in [("e2", "e4"), ("e7", "e5"), ("b1", "c3"), ("b8", "c6")] { board.move_by_algebraic(f, t).unwrap(); }
+        assert!(matches!(parse_move(&board, "knight e2"), Err(CommandError::Ambiguous(m)) if m.len() == 2));
+        assert_eq!(parse_move(&board, "knight c e2").map(|m| m.from), Ok(sq("c3")));
+        assert_eq!(parse_move(&board, "g1 e2").map(|m| m.from), Ok(sq("g1")));
+    }
+}
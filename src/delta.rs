@@ -0,0 +1,244 @@
+//! Compact position updates for spectators, sending only the tiles that changed.
+
+use std::fmt;
+
+use crate::{position_checksum, CastlingRights, ChessBoard, Color, PieceKind, Square};
+
+/// Piece kinds in the order of their codes, see `piece_code()`.
+const KINDS: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Rook, PieceKind::Knight, PieceKind::Bishop, PieceKind::Queen, PieceKind::King];
+
+/// Why a delta couldn't be decoded or applied.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DeltaError {
+    /// The encoded delta is cut short or holds invalid values.
+    Malformed,
+    /// The position after applying the delta doesn't match the sender's, e.g. because a delta was lost.
+    ChecksumMismatch
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            DeltaError::Malformed => { write!(f, "malformed delta") }
+            DeltaError::ChecksumMismatch => { write!(f, "position doesn't match after applying delta") }
+        };
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
+/// What a spectator needs to show a position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct BoardView {
+    /// Tiles indexed like `ChessBoard::get_board()`.
+    pub squares: [Option<(PieceKind, Color)>; 64],
+    pub white_turn: bool,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>
+}
+
+/// Changes from one `BoardView` to the next.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Delta {
+    /// Tiles that changed and what is on them now.
+    pub changes: Vec<(Square, Option<(PieceKind, Color)>)>,
+    pub white_turn: bool,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    /// `BoardView::checksum()` of the position after the delta.
+    pub checksum: u64
+}
+
+impl BoardView {
+    /// Get the view of a board, e.g. to send in full to a new spectator.
+    pub fn new(board: &ChessBoard) -> BoardView {
+        return BoardView {
+            squares: board.get_board(),
+            white_turn: board.get_player(),
+            castling: board.castling_rights(),
+            en_passant: board.en_passant_square()
+        };
+    }
+
+    /**
+    Get the changes needed to turn this view into another.                  <br/>
+    Parameters:                                                             <br/>
+    `next`: View to change into                                             <br/>
+    Returns:                                                                <br/>
+    The delta, holding only the tiles that differ.
+    */
+    pub fn diff(&self, next: &BoardView) -> Delta {
+        let changes = (0..64usize)
+            .filter(|&i| self.squares[i] != next.squares[i])
+            .map(|i| (Square::new(i).unwrap(), next.squares[i]))
+            .collect();
+
+        return Delta {
+            changes,
+            white_turn: next.white_turn,
+            castling: next.castling,
+            en_passant: next.en_passant,
+            checksum: next.checksum()
+        };
+    }
+
+    /**
+    Apply a delta and verify the result with its checksum.                  <br/>
+    Parameters:                                                             <br/>
+    `delta`: Delta from `diff()`                                            <br/>
+    Returns:                                                                <br/>
+    `Ok(())`, or `DeltaError::ChecksumMismatch` if the result doesn't match the sender's
+    position. The view is left unchanged on error, so the spectator can ask for a full view.
+    */
+    pub fn apply(&mut self, delta: &Delta) -> Result<(), DeltaError> {
+        let mut next = *self;
+        for (square, piece) in delta.changes.iter() { next.squares[square.index()] = *piece; }
+        next.white_turn = delta.white_turn;
+        next.castling = delta.castling;
+        next.en_passant = delta.en_passant;
+
+        if next.checksum() != delta.checksum { return Err(DeltaError::ChecksumMismatch); }
+
+        *self = next;
+        return Ok(());
+    }
+
+    /// Get a 64-bit FNV-1a hash of the view, the same as `ChessBoard::state_checksum()` of the position.
+    pub fn checksum(&self) -> u64 {
+        return position_checksum(&self.squares, self.white_turn, self.castling, self.en_passant);
+    }
+}
+
+impl Delta {
+    /**
+    Encode the delta for sending. Takes 11 bytes plus 2 per changed tile.   <br/>
+    Returns:                                                                <br/>
+    The encoded delta.
+    */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(11 + self.changes.len() * 2);
+        bytes.extend([state_bits(self.white_turn, self.castling), self.en_passant.map_or(0xff, |s| s.index() as u8)]);
+        bytes.extend(self.checksum.to_le_bytes());
+        bytes.push(self.changes.len() as u8);
+
+        for (square, piece) in self.changes.iter() { bytes.extend([square.index() as u8, piece_code(*piece)]); }
+
+        return bytes;
+    }
+
+    /**
+    Decode a delta encoded with `to_bytes()`.                               <br/>
+    Parameters:                                                             <br/>
+    `bytes`: Encoded delta                                                  <br/>
+    Returns:                                                                <br/>
+    The delta, or `DeltaError::Malformed` if the bytes aren't a valid delta.
+    */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Delta, DeltaError> {
+        if bytes.len() < 11 || bytes.len() != 11 + bytes[10] as usize * 2 || bytes[0] > 0x1f { return Err(DeltaError::Malformed); }
+
+        let en_passant = match bytes[1] {
+            0xff => { None }
+            i => { Some(Square::new(i as usize).ok_or(DeltaError::Malformed)?) }
+        };
+
+        let mut changes = vec![];
+        for c in bytes[11..].chunks(2) {
+            let square = Square::new(c[0] as usize).ok_or(DeltaError::Malformed)?;
+            changes.push((square, piece_from_code(c[1]).ok_or(DeltaError::Malformed)?));
+        }
+
+        return Ok(Delta {
+            changes,
+            white_turn: bytes[0] & 1 != 0,
+            castling: CastlingRights {
+                white_kingside: bytes[0] & 2 != 0,
+                white_queenside: bytes[0] & 4 != 0,
+                black_kingside: bytes[0] & 8 != 0,
+                black_queenside: bytes[0] & 16 != 0
+            },
+            en_passant,
+            checksum: u64::from_le_bytes(bytes[2..10].try_into().unwrap())
+        });
+    }
+}
+
+/// Pack the side to move and castling rights into one byte.
+fn state_bits(white_turn: bool, castling: CastlingRights) -> u8 {
+    return white_turn as u8 | (castling.white_kingside as u8) << 1 | (castling.white_queenside as u8) << 2
+        | (castling.black_kingside as u8) << 3 | (castling.black_queenside as u8) << 4;
+}
+
+/// Get the code of a tile: 0 if empty, otherwise the index in `KINDS` plus 1, plus 8 for black.
+fn piece_code(piece: Option<(PieceKind, Color)>) -> u8 {
+    return match piece {
+        None => { 0 }
+        Some((kind, color)) => { KINDS.iter().position(|k| *k == kind).unwrap() as u8 + 1 + (color as u8) * 8 }
+    };
+}
+
+/// Get a tile from its code, or `None` if the code is invalid.
+fn piece_from_code(code: u8) -> Option<Option<(PieceKind, Color)>> {
+    if code == 0 { return Some(None); }
+    if code > 15 { return None; }
+
+    let color = if code & 8 == 0 { Color::White } else { Color::Black };
+    let kind = *KINDS.get(((code & 7) as usize).checked_sub(1)?)?;
+
+    return Some(Some((kind, color)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_follow_moves() {
+        let mut board = ChessBoard::new();
+        let mut spectator = BoardView::new(&board);
+        let mut last = spectator;
+
+        for (f, t) in [("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("g8", "f6"), ("f1", "b5"), ("c7", "c6"), ("d5", "c6"), ("f6", "g4"), ("g1", "f3"), ("g4", "e5"), ("e1", "g1")] {
+            board.move_by_algebraic(f, t).unwrap();
+            let view = BoardView::new(&board);
+            let bytes = last.diff(&view).to_bytes();
+            last = view;
+
+            spectator.apply(&Delta::from_bytes(&bytes).unwrap()).unwrap();
+            assert_eq!(spectator, view);
+        }
+
+        // Castling changes four tiles.
+        board.undo();
+        assert_eq!(BoardView::new(&board).diff(&last).changes.len(), 4);
+    }
+
+    #[test]
+    fn checksum_matches_board() {
+        let mut board = ChessBoard::new();
+        for (f, t) in [("e2", "e4"), ("d7", "d5"), ("e4", "e5"), ("f7", "f5"), ("e1", "e2")] {
+            assert_eq!(BoardView::new(&board).checksum(), board.state_checksum());
+            board.move_by_algebraic(f, t).unwrap();
+        }
+        assert_eq!(BoardView::new(&board).checksum(), board.state_checksum());
+    }
+
+    #[test]
+    fn bad_deltas() {
+        let board = ChessBoard::new();
+        let mut view = BoardView::new(&board);
+        let mut delta = view.diff(&view);
+        assert!(delta.changes.is_empty());
+        assert_eq!(delta.to_bytes().len(), 11);
+
+        delta.changes.push(("e4".parse().unwrap(), Some((PieceKind::Queen, Color::Black))));
+        assert_eq!(view.apply(&delta), Err(DeltaError::ChecksumMismatch));
+        assert_eq!(view, BoardView::new(&board));
+
+        let bytes = delta.to_bytes();
+        assert_eq!(Delta::from_bytes(&bytes), Ok(delta));
+        assert_eq!(Delta::from_bytes(&bytes[..12]), Err(DeltaError::Malformed));
+        let mut bad = bytes.clone();
+        bad[12] = 7;
+        assert_eq!(Delta::from_bytes(&bad), Err(DeltaError::Malformed));
+    }
+}
@@ -0,0 +1,186 @@
+//! Support for electronic boards that only report which squares are occupied.
+
+use std::fmt;
+
+use crate::{ChessBoard, Move, Square};
+
+/// Piece lifted from or placed on a square of an electronic board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BoardEvent {
+    Lift(Square),
+    Place(Square)
+}
+
+/// Driver for an electronic board.
+pub trait EBoard {
+    /// Get the next event from the board, or `None` if nothing happened.
+    fn poll(&mut self) -> Option<BoardEvent>;
+}
+
+/// Why a board event was rejected.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum EBoardError {
+    /// A piece was lifted from an empty square.
+    LiftFromEmpty(Square),
+    /// A piece was placed on an occupied square.
+    PlaceOnOccupied(Square),
+    /// The occupied squares don't match any legal move, or match several.
+    NoMatchingMove
+}
+
+impl fmt::Display for EBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            EBoardError::LiftFromEmpty(s) => { write!(f, "piece lifted from empty square {}", s) }
+            EBoardError::PlaceOnOccupied(s) => { write!(f, "piece placed on occupied square {}", s) }
+            EBoardError::NoMatchingMove => { write!(f, "occupied squares don't match a legal move") }
+        };
+    }
+}
+
+impl std::error::Error for EBoardError {}
+
+/**
+Follows what happens on an electronic board and works out which legal move it adds up to.   <br/>
+Create a new tracker with `new()` after every move played on the `ChessBoard`.
+*/
+pub struct MoveTracker {
+    /// Occupied squares when the tracker was created.
+    start: u64,
+    /// Occupied squares now.
+    occupancy: u64,
+    /// Last square a piece was placed on.
+    placed: Option<Square>
+}
+
+impl MoveTracker {
+    /// Start tracking from the current position.
+    pub fn new(board: &ChessBoard) -> MoveTracker {
+        let occupancy = board.occupancy();
+        return MoveTracker { start: occupancy, occupancy, placed: None };
+    }
+
+    /**
+    Feed an event from the board.                                                   <br/>
+    Returns:                                                                        <br/>
+    `Ok(Some(move))` once the events add up to a legal move, `Ok(None)` while the
+    move is still in progress, or an error for an impossible event.
+    */
+    pub fn event(&mut self, board: &ChessBoard, event: BoardEvent) -> Result<Option<Move>, EBoardError> {
+        match event {
+            BoardEvent::Lift(s) => {
+                if self.occupancy & bit(s) == 0 { return Err(EBoardError::LiftFromEmpty(s)); }
+                self.occupancy &= !bit(s);
+
+                // A move is only done once a piece is put down.
+                return Ok(None);
+            }
+            BoardEvent::Place(s) => {
+                if self.occupancy & bit(s) != 0 { return Err(EBoardError::PlaceOnOccupied(s)); }
+                self.occupancy |= bit(s);
+                self.placed = Some(s);
+            }
+        }
+
+        return Ok(self.find_move(board));
+    }
+
+    /**
+    Feed a full occupancy snapshot from the board. Bit `i` is set if the square with    <br/>
+    index `i` is occupied, the same as `ChessBoard::occupancy()`.                       <br/>
+    Returns:                                                                            <br/>
+    The legal move leading to the snapshot, or an error if there is no such move or
+    several, e.g. two different captures by the same piece.
+    */
+    pub fn snapshot(&mut self, board: &ChessBoard, occupancy: u64) -> Result<Move, EBoardError> {
+        self.occupancy = occupancy;
+        self.placed = None;
+
+        return self.find_move(board).ok_or(EBoardError::NoMatchingMove);
+    }
+
+    /// Find the only legal move that leads to the current occupancy.
+    fn find_move(&self, board: &ChessBoard) -> Option<Move> {
+        let mut found: Vec<Move> = board.legal_moves()
+            .filter(|m| occupancy_after(self.start, *m) == self.occupancy)
+            .collect();
+
+        // Captures by the same piece leave the same tiles occupied, so use where it was put down.
+        if found.len() > 1 {
+            found.retain(|m| Some(m.to) == self.placed);
+        }
+
+        return if found.len() == 1 { Some(found[0]) } else { None };
+    }
+}
+
+/// Get the occupancy bit of a square.
+fn bit(square: Square) -> u64 { return 1 << square.index(); }
+
+/// Get the occupied squares after a move.
+fn occupancy_after(occupancy: u64, m: Move) -> u64 {
+    let mut after = occupancy & !bit(m.from);
+    if let Some(c) = m.captured_square() { after &= !bit(c); }
+    after |= bit(m.to);
+
+    if let Some((rook_from, rook_to)) = m.rook_squares() {
+        after = (after & !bit(rook_from)) | bit(rook_to);
+    }
+
+    return after;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MoveKind;
+
+    fn sq(s: &str) -> Square { return s.parse().unwrap(); }
+
+    #[test]
+    fn events_add_up_to_a_move() {
+        let mut board = ChessBoard::new();
+        let mut tracker = MoveTracker::new(&board);
+
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("e4"))), Err(EBoardError::LiftFromEmpty(sq("e4"))));
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("e2"))), Ok(None));
+        let m = tracker.event(&board, BoardEvent::Place(sq("e4"))).unwrap().unwrap();
+        assert_eq!((m.from, m.to, m.kind), (sq("e2"), sq("e4"), MoveKind::DoubleStep));
+
+        for (f, t) in [("e2", "e4"), ("d7", "d5")] { board.move_by_algebraic(f, t).unwrap(); }
+
+        // Capture, with the captured piece lifted first.
+        let mut tracker = MoveTracker::new(&board);
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("d5"))), Ok(None));
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("e4"))), Ok(None));
+        assert_eq!(tracker.event(&board, BoardEvent::Place(sq("d2"))), Err(EBoardError::PlaceOnOccupied(sq("d2"))));
+        let m = tracker.event(&board, BoardEvent::Place(sq("d5"))).unwrap().unwrap();
+        assert_eq!((m.from, m.to, m.kind), (sq("e4"), sq("d5"), MoveKind::Capture));
+    }
+
+    #[test]
+    fn castling_needs_the_rook() {
+        let mut board = ChessBoard::new();
+        for (f, t) in [("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6")] {
+            board.move_by_algebraic(f, t).unwrap();
+        }
+
+        let mut tracker = MoveTracker::new(&board);
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("e1"))), Ok(None));
+        assert_eq!(tracker.event(&board, BoardEvent::Place(sq("g1"))), Ok(None));
+        assert_eq!(tracker.event(&board, BoardEvent::Lift(sq("h1"))), Ok(None));
+        let m = tracker.event(&board, BoardEvent::Place(sq("f1"))).unwrap().unwrap();
+        assert_eq!(m.kind, MoveKind::KingsideCastle);
+    }
+
+    #[test]
+    fn snapshots() {
+        let board = ChessBoard::new();
+        let mut tracker = MoveTracker::new(&board);
+        let occupancy = board.occupancy();
+
+        let m = tracker.snapshot(&board, (occupancy & !(1 << 62)) | (1 << 45)).unwrap();
+        assert_eq!((m.from, m.to), (sq("g1"), sq("f3")));
+        assert_eq!(tracker.snapshot(&board, occupancy & !(1 << 62)), Err(EBoardError::NoMatchingMove));
+    }
+}
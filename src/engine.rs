@@ -0,0 +1,781 @@
+//! Built-in opponent for single-player games, searching with alpha-beta.
+
+pub mod eval;
+pub mod table;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::backend::{EngineBackend, EngineOption, SearchLimits};
+use crate::{splitmix64, ChessBoard, Color, Move, PieceKind, Square};
+use eval::{value, Evaluator, Standard};
+use table::{Bound, Entry, TranspositionTable};
+
+/// Score of being checkmated right away. Mates further away score closer to zero, so faster mates are preferred.
+const MATE: i32 = 1_000_000;
+
+/// Scores this close to `MATE` are forced mates, which deeper searches can't improve on.
+const MATE_FOUND: i32 = MATE - 1000;
+
+/// Depth searched when the limits don't give one.
+const DEFAULT_DEPTH: u32 = 3;
+
+/// Deepest iteration when searching for a time budget.
+const MAX_DEPTH: u32 = 64;
+
+/// Size in MB of the table of a single search, which is thrown away after it. Keep one with `search_with_table()` or `Engine` for more.
+const ONE_OFF_TABLE_MB: usize = 1;
+
+/// How many nodes are searched between checks of the time budget.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Moves expected until the end of the game when the time control doesn't say.
+const MOVES_LEFT_GUESS: u32 = 30;
+
+/// Skill level of the full strength engine. Lower levels play weaker, down to 1.
+pub const MAX_SKILL: u8 = 10;
+
+/// Centipawns the evaluation may be off by per level below `MAX_SKILL`.
+const NOISE_PER_LEVEL: i32 = 25;
+
+/// Chance in percent per level below `MAX_SKILL` of playing one of the next best moves.
+const MISTAKE_PER_LEVEL: u64 = 4;
+
+/// Moves searched when the engine is going to make a mistake: the best move and the ones it picks from.
+const MISTAKE_LINES: usize = 3;
+
+/// Outcome of a search.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct SearchResult {
+    /// Best move found, or `None` if there are no legal moves.
+    pub best_move: Option<Move>,
+    /// Score of the best move for the side to move, in centipawns.
+    pub score: i32,
+    /// Depth of the last iteration that finished.
+    pub depth: u32,
+    /// Moves both sides are expected to play, starting with the best move.
+    pub pv: Vec<Move>,
+    /// Number of positions searched.
+    pub nodes: u64
+}
+
+impl SearchResult {
+    /// Get the number of moves until mate if the score is a forced mate, negative if the side to move gets mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE_FOUND { return None; }
+
+        let plies = MATE - self.score.abs();
+        return Some(if self.score > 0 { (plies + 1) / 2 } else { -(plies / 2) });
+    }
+}
+
+/**
+Find the best move for the side to move by looking at every line of play, pruning lines    <br/>
+that can't change the result.                                                              <br/>
+Parameters:                                                                                <br/>
+`board`: Position to search                                                                <br/>
+`depth`: Number of half moves to look ahead, at least 1                                    <br/>
+Returns:                                                                                   <br/>
+The best move, or `None` if the game has ended or a promotion is pending.
+*/
+pub fn best_move(board: &ChessBoard, depth: u32) -> Option<Move> {
+    return search(board, depth, None).best_move;
+}
+
+/**
+Search one half move deeper at a time until the depth or time budget is reached. If time   <br/>
+runs out, the result of the last finished depth is returned, so the budget is kept even    <br/>
+for large depths. It uses a small transposition table of its own; for a game, keep one      <br/>
+with `search_with_table()` or use an `Engine`.                                             <br/>
+Parameters:                                                                                <br/>
+`board`: Position to search                                                                <br/>
+`max_depth`: Deepest search, at least 1                                                    <br/>
+`budget`: Time to search for, or `None` to search to `max_depth`                           <br/>
+Returns:                                                                                   <br/>
+The best move with its score and principal variation.
+*/
+pub fn search(board: &ChessBoard, max_depth: u32, budget: Option<Duration>) -> SearchResult {
+    return search_with_table(board, max_depth, budget, &mut TranspositionTable::new(ONE_OFF_TABLE_MB), &Standard);
+}
+
+/**
+Search like `search()`, with a transposition table kept between searches, e.g. one per game,  <br/>
+and an evaluation of choice. Clear the table when switching evaluations.                       <br/>
+Parameters:                                                                                    <br/>
+`board`: Position to search                                                                    <br/>
+`max_depth`: Deepest search, at least 1                                                        <br/>
+`budget`: Time to search for, or `None` to search to `max_depth`                               <br/>
+`table`: Positions searched before                                                             <br/>
+`evaluator`: Scores the positions where the search stops                                       <br/>
+Returns:                                                                                       <br/>
+The best move with its score and principal variation.
+*/
+pub fn search_with_table(board: &ChessBoard, max_depth: u32, budget: Option<Duration>, table: &mut TranspositionTable, evaluator: &dyn Evaluator) -> SearchResult {
+    let limits = SearchLimits { movetime: budget, ..SearchLimits::default() };
+    return Search::new(board, &limits, None, table, evaluator).run(board, max_depth);
+}
+
+/**
+Search within limits like a GUI gives them, from a fixed depth or node count to a share of   <br/>
+the clock in a blitz game. The search stops at the first limit reached. With a clock, the     <br/>
+time for the move is the time left spread over the moves to go, plus most of the increment.   <br/>
+Parameters:                                                                                   <br/>
+`board`: Position to search                                                                   <br/>
+`limits`: When to stop. `infinite` is ignored, as there is no way to stop the search here.   <br/>
+Returns:                                                                                      <br/>
+The best move with its score and principal variation. With no limits at all, the search goes  <br/>
+on until a depth of 64, which takes very long.
+*/
+pub fn search_with_limits(board: &ChessBoard, limits: &SearchLimits) -> SearchResult {
+    let mut table = TranspositionTable::new(ONE_OFF_TABLE_MB);
+    return Search::new(board, limits, None, &mut table, &Standard).run(board, limits.depth.unwrap_or(MAX_DEPTH));
+}
+
+/**
+Search the best few moves, e.g. to show alternatives to the best move in an analysis board.   <br/>
+Deepens like `search()`, so each line is searched to the same depth.                          <br/>
+Parameters:                                                                                   <br/>
+`board`: Position to search                                                                   <br/>
+`lines`: Number of moves to find, at least 1                                                  <br/>
+`max_depth`: Deepest search, at least 1                                                       <br/>
+`budget`: Time to search for, or `None` to search to `max_depth`                              <br/>
+Returns:                                                                                      <br/>
+A result per move, best first. Fewer if there aren't as many legal moves, none if the game has ended.
+*/
+pub fn analyze(board: &ChessBoard, lines: usize, max_depth: u32, budget: Option<Duration>) -> Vec<SearchResult> {
+    let mut table = TranspositionTable::new(ONE_OFF_TABLE_MB);
+    let limits = SearchLimits { movetime: budget, ..SearchLimits::default() };
+    return Search::new(board, &limits, None, &mut table, &Standard).run_lines(board, max_depth, lines);
+}
+
+/// State of a running search.
+struct Search<'a> {
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    /// Set from outside to stop right away, e.g. by a protocol adapter.
+    stop: Option<&'a AtomicBool>,
+    nodes: u64,
+    /// Time ran out, so the current iteration has to be thrown away.
+    aborted: bool,
+    /// Moves left out at the root because they are already in other lines.
+    excluded: Vec<Move>,
+    /// Positions searched before, including by earlier iterations.
+    table: &'a mut TranspositionTable,
+    evaluator: &'a dyn Evaluator
+}
+
+impl<'a> Search<'a> {
+    /// Get a search of a position within limits, starting the clock now.
+    fn new(board: &ChessBoard, limits: &SearchLimits, stop: Option<&'a AtomicBool>, table: &'a mut TranspositionTable, evaluator: &'a dyn Evaluator) -> Search<'a> {
+        let color = if board.get_player() { Color::White } else { Color::Black };
+        let deadline = time_budget(limits, color).map(|t| Instant::now() + t);
+        return Search { deadline, max_nodes: limits.nodes, stop, nodes: 0, aborted: false, excluded: vec![], table, evaluator };
+    }
+
+    /// Deepen the search until `max_depth`, the deadline or a forced mate is reached.
+    fn run(&mut self, board: &ChessBoard, max_depth: u32) -> SearchResult {
+        let result = self.run_lines(board, max_depth, 1).pop();
+        return result.unwrap_or(SearchResult { best_move: None, score: 0, depth: 0, pv: vec![], nodes: self.nodes });
+    }
+
+    /// Deepen the search for the best `lines` moves. Each is found by searching again without the ones found before.
+    fn run_lines(&mut self, board: &ChessBoard, max_depth: u32, lines: usize) -> Vec<SearchResult> {
+        let mut board = board.clone();
+        let moves = if board.is_game_ended() { vec![] } else { legal_moves(&board) };
+        if moves.is_empty() { return vec![]; }
+        let lines = lines.clamp(1, moves.len());
+
+        // Have moves ready even if the first iteration doesn't finish.
+        let mut results: Vec<SearchResult> = moves.iter().take(lines).map(|m| SearchResult { best_move: Some(*m), score: 0, depth: 0, pv: vec![], nodes: 0 }).collect();
+
+        for depth in 1..=max_depth.max(1) {
+            let mut found: Vec<SearchResult> = vec![];
+            while found.len() < lines && !self.aborted {
+                self.excluded = found.iter().filter_map(|r| r.best_move).collect();
+                let mut pv = vec![];
+                let score = self.alpha_beta(&mut board, depth, -MATE - 1, MATE + 1, 0, &mut pv);
+                found.push(SearchResult { best_move: pv.first().copied(), score, depth, pv, nodes: 0 });
+            }
+            if self.aborted { break; }
+
+            results = found;
+            if results.iter().all(|r| r.score.abs() >= MATE_FOUND) { break; }
+        }
+
+        self.excluded.clear();
+        for r in &mut results { r.nodes = self.nodes; }
+        return results;
+    }
+
+    /// Count a node, and abort if it's one too many, the deadline passed or the search was told to stop.
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.max_nodes.is_some_and(|n| self.nodes > n) { self.aborted = true; }
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) { return; }
+
+        if self.deadline.is_some_and(|d| Instant::now() >= d) || self.stop.is_some_and(|s| s.load(Ordering::Relaxed)) { self.aborted = true; }
+    }
+
+    /**
+    Score the position for the side to move by looking `depth` half moves ahead, where only   <br/>
+    scores between `alpha` and `beta` matter. `ply` counts the half moves from the root, and   <br/>
+    `pv` gets the best line from here.
+    */
+    fn alpha_beta(&mut self, board: &mut ChessBoard, depth: u32, mut alpha: i32, beta: i32, ply: usize, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        self.count_node();
+        if self.aborted { return 0; }
+
+        let mut moves = legal_moves(board);
+        if moves.is_empty() {
+            let color = if board.get_player() { Color::White } else { Color::Black };
+            return if board.is_in_check(color) { -MATE + ply as i32 } else { 0 };
+        }
+        if depth == 0 { return self.quiescence(board, alpha, beta); }
+        if ply == 0 { moves.retain(|m| !self.excluded.contains(m)); }
+
+        let key = board.zobrist_hash();
+        let known = self.table.probe(key);
+        if let Some(e) = known.filter(|e| ply > 0 && e.depth >= depth) {
+            let score = from_table(e.score, ply);
+            let usable = match e.bound {
+                Bound::Exact => { true }
+                Bound::Lower => { score >= beta }
+                Bound::Upper => { score <= alpha }
+            };
+            if usable {
+                if e.bound == Bound::Exact { self.line_from_table(board, e.best, depth, pv); }
+                return score;
+            }
+        }
+
+        // Good moves first make more cutoffs: the best move found before, then captures of valuable pieces.
+        order_captures(board, &mut moves);
+        if let Some(i) = known.and_then(|e| e.best).and_then(|b| moves.iter().position(|m| *m == b)) {
+            let m = moves.remove(i);
+            moves.insert(0, m);
+        }
+
+        let original_alpha = alpha;
+        let mut line = vec![];
+        for m in moves {
+            let Ok(undo) = board.make(m) else { continue; };
+            let score = -self.alpha_beta(board, depth - 1, -beta, -alpha, ply + 1, &mut line);
+            board.unmake(undo);
+            if self.aborted { return 0; }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(m);
+                pv.extend_from_slice(&line);
+                if alpha >= beta { break; }
+            }
+        }
+
+        let bound = if alpha >= beta { Bound::Lower } else if alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+        let best = if bound == Bound::Upper { known.and_then(|e| e.best) } else { pv.first().copied() };
+        self.table.store(Entry { key, depth, score: to_table(alpha, ply), bound, best });
+
+        return alpha;
+    }
+
+    /**
+    Score a position at the end of the main search by playing out the captures, so a piece    <br/>
+    taken at the horizon isn't counted as won when it can be taken back. The side to move      <br/>
+    can also stop capturing and keep the evaluation.
+    */
+    fn quiescence(&mut self, board: &mut ChessBoard, mut alpha: i32, beta: i32) -> i32 {
+        self.count_node();
+        if self.aborted { return 0; }
+
+        let stand_pat = self.evaluator.eval(board);
+        if stand_pat >= beta { return beta; }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves = captures(board);
+        order_captures(board, &mut moves);
+        for m in moves {
+            let Ok(undo) = board.make(m) else { continue; };
+            let score = -self.quiescence(board, -beta, -alpha);
+            board.unmake(undo);
+            if self.aborted { return 0; }
+
+            if score >= beta { return beta; }
+            alpha = alpha.max(score);
+        }
+
+        return alpha;
+    }
+
+    /// Rebuild the principal variation from a stored exact score by following the best moves in the table.
+    fn line_from_table(&self, board: &mut ChessBoard, first: Option<Move>, depth: u32, pv: &mut Vec<Move>) {
+        let mut undos = vec![];
+        let mut next = first;
+
+        while let Some(m) = next.filter(|m| pv.len() < depth as usize && board.is_legal(*m)) {
+            let Ok(undo) = board.make(m) else { break; };
+            pv.push(m);
+            undos.push(undo);
+            next = self.table.probe(board.zobrist_hash()).filter(|e| e.bound == Bound::Exact).and_then(|e| e.best);
+        }
+
+        while let Some(undo) = undos.pop() { board.unmake(undo); }
+    }
+}
+
+/// Convert a score to store in the table. Mate scores count from the root, but are stored counting from the position.
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_FOUND { return score + ply as i32; }
+    if score <= -MATE_FOUND { return score - ply as i32; }
+    return score;
+}
+
+/// Convert a score from the table back to count mates from the root.
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_FOUND { return score - ply as i32; }
+    if score <= -MATE_FOUND { return score + ply as i32; }
+    return score;
+}
+
+/// Get the legal moves, promoting pawns to queens. Works after `make()`, unlike `ChessBoard::legal_moves()`.
+fn legal_moves(board: &ChessBoard) -> Vec<Move> {
+    let mut moves = board.pseudo_legal_moves();
+    moves.retain(|m| board.is_legal(*m));
+    return promote_to_queens(board, moves);
+}
+
+/// Get the legal captures, promoting pawns to queens.
+fn captures(board: &ChessBoard) -> Vec<Move> {
+    let mut moves = board.pseudo_legal_moves();
+    moves.retain(|m| m.captured_square().is_some() && board.is_legal(*m));
+    return promote_to_queens(board, moves);
+}
+
+/// Set pawn moves to the last rank to promote to a queen.
+fn promote_to_queens(board: &ChessBoard, mut moves: Vec<Move>) -> Vec<Move> {
+    for m in &mut moves {
+        let last_rank = m.to.rank().index() == 0 || m.to.rank().index() == 7;
+        if last_rank && board.piece_at(m.from).is_some_and(|p| p.0 == PieceKind::Pawn) { m.promotion = Some(PieceKind::Queen); }
+    }
+
+    return moves;
+}
+
+/// Sort captures of valuable pieces first, by cheap pieces first among them. Other moves keep their order after the captures.
+fn order_captures(board: &ChessBoard, moves: &mut [Move]) {
+    let worth = |s: Square| board.piece_at(s).map_or(0, |p| value(p.0));
+    moves.sort_by_key(|m| if let Some(c) = m.captured_square() { -10 * worth(c) + worth(m.from) / 100 } else { 0 });
+}
+
+/**
+`search()` as a backend for the protocol adapters. Searches to the depth in the limits, for    <br/>
+the move time, or to `depth` if the limits give neither. The transposition table is kept       <br/>
+until a new game, and its size is set with the "Hash" option in megabytes.                     <br/>
+Below `MAX_SKILL`, e.g. for beginners, it looks fewer moves ahead, misjudges positions and      <br/>
+sometimes plays one of the next best moves. The level is also the "Skill Level" option.
+*/
+pub struct Engine<E: Evaluator = Standard> {
+    pub depth: u32,
+    /// How well to play, from 1 to `MAX_SKILL`.
+    pub skill: u8,
+    /// Random state for the mistakes of lower skill levels.
+    rng: u64,
+    table: TranspositionTable,
+    evaluator: E
+}
+
+impl Default for Engine {
+    fn default() -> Engine { return Engine::with_evaluator(Standard); }
+}
+
+impl<E: Evaluator> Engine<E> {
+    /// Get an engine scoring positions with another evaluation.
+    pub fn with_evaluator(evaluator: E) -> Engine<E> {
+        let rng = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_nanos() as u64);
+        return Engine { depth: DEFAULT_DEPTH, skill: MAX_SKILL, rng, table: TranspositionTable::default(), evaluator };
+    }
+
+    /// Seed the mistakes of lower skill levels, so they can be reproduced, e.g. in tests.
+    pub fn seed(&mut self, seed: u64) { self.rng = seed; }
+
+    /**
+    Search like `search_with_limits()`, keeping what was learned for the next move. Searches  <br/>
+    to `depth` if the limits give no depth, nodes or time.                                     <br/>
+    Parameters:                                                                                <br/>
+    `board`: Position to search                                                                <br/>
+    `limits`: When to stop                                                                     <br/>
+    `stop`: Stops the search right away when set, e.g. from another thread                     <br/>
+    Returns:                                                                                   <br/>
+    The best move with its score and principal variation.
+    */
+    pub fn think(&mut self, board: &ChessBoard, limits: &SearchLimits, stop: Option<&AtomicBool>) -> SearchResult {
+        let limited = limits.nodes.is_some() || limits.movetime.is_some() || limits.time[0].is_some() || limits.time[1].is_some() || limits.infinite;
+        let depth = limits.depth.unwrap_or(if limited { MAX_DEPTH } else { self.depth });
+        if self.skill >= MAX_SKILL { return Search::new(board, limits, stop, &mut self.table, &self.evaluator).run(board, depth); }
+
+        let skill = self.skill.max(1);
+        let weakness = MAX_SKILL - skill;
+        let depth = depth.min(skill as u32 / 2 + 1);
+        let noisy = Noisy { evaluator: &self.evaluator, amplitude: weakness as i32 * NOISE_PER_LEVEL, seed: splitmix64(&mut self.rng) };
+        let mistake = splitmix64(&mut self.rng) % 100 < weakness as u64 * MISTAKE_PER_LEVEL;
+
+        // The noise changes every move, so its scores are kept out of the engine's table.
+        let mut table = TranspositionTable::new(1);
+        let mut results = Search::new(board, limits, stop, &mut table, &noisy).run_lines(board, depth, if mistake { MISTAKE_LINES } else { 1 });
+        if results.len() < 2 { return results.pop().unwrap_or(SearchResult { best_move: None, score: 0, depth: 0, pv: vec![], nodes: 0 }); }
+
+        let pick = 1 + splitmix64(&mut self.rng) as usize % (results.len() - 1);
+        return results.swap_remove(pick);
+    }
+}
+
+/// Evaluation that is off by up to `amplitude` centipawns, by the same amount for a position throughout a search.
+struct Noisy<'a> {
+    evaluator: &'a dyn Evaluator,
+    amplitude: i32,
+    seed: u64
+}
+
+impl Evaluator for Noisy<'_> {
+    fn eval(&self, board: &ChessBoard) -> i32 {
+        let mut state = board.zobrist_hash() ^ self.seed;
+        let noise = (splitmix64(&mut state) % (2 * self.amplitude as u64 + 1)) as i32 - self.amplitude;
+        return self.evaluator.eval(board) + noise;
+    }
+}
+
+/// Get how long to search for: the move time if given, otherwise a share of the clock of the side to move.
+fn time_budget(limits: &SearchLimits, color: Color) -> Option<Duration> {
+    if limits.infinite { return None; }
+    if limits.movetime.is_some() { return limits.movetime; }
+
+    let left = limits.time[color as usize]?;
+    let moves = limits.moves_to_go.unwrap_or(MOVES_LEFT_GUESS).max(1);
+    let share = left / moves + limits.increment[color as usize] * 3 / 4;
+
+    // Leave some time on the clock even on the last move before the time control.
+    return Some(share.min(left * 4 / 5));
+}
+
+impl<E: Evaluator> EngineBackend for Engine<E> {
+    fn name(&self) -> String { return "emilaa-chess".to_string(); }
+
+    fn options(&self) -> Vec<EngineOption> {
+        return vec![
+            EngineOption { name: "Hash".to_string(), default: table::DEFAULT_SIZE_MB as i64, min: 1, max: 4096 },
+            EngineOption { name: "Skill Level".to_string(), default: MAX_SKILL as i64, min: 1, max: MAX_SKILL as i64 }
+        ];
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        if let (true, Ok(mb @ 1..=4096)) = (name.eq_ignore_ascii_case("hash"), value.trim().parse::<usize>()) {
+            self.table = TranspositionTable::new(mb);
+        }
+        if let (true, Ok(skill @ 1..=MAX_SKILL)) = (name.eq_ignore_ascii_case("skill level"), value.trim().parse::<u8>()) {
+            self.skill = skill;
+        }
+    }
+
+    fn new_game(&mut self) { self.table.clear(); }
+
+    fn search(&mut self, board: &ChessBoard, limits: &SearchLimits, stop: &AtomicBool) -> Option<Move> {
+        return self.think(board, limits, Some(stop)).best_move;
+    }
+}
+
+/**
+Opponent playing random legal moves, e.g. for fuzzing games or as the weakest possible bot.   <br/>
+Its moves only depend on the seed and the positions, so games can be replayed exactly.
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct RandomPlayer {
+    rng: u64
+}
+
+impl RandomPlayer {
+    /// Get a player whose moves are determined by `seed`.
+    pub fn new(seed: u64) -> RandomPlayer { return RandomPlayer { rng: seed }; }
+
+    /**
+    Pick one of the legal moves, each as likely as the others.                            <br/>
+    Parameters:                                                                          <br/>
+    `board`: Position to move in                                                         <br/>
+    Returns:                                                                             <br/>
+    The move, with a random promotion piece for a pawn reaching the last rank, or `None`  <br/>
+    if there are no legal moves.
+    */
+    pub fn choose(&mut self, board: &ChessBoard) -> Option<Move> {
+        let moves: Vec<Move> = board.legal_moves().collect();
+        if moves.is_empty() { return None; }
+
+        let mut m = moves[(splitmix64(&mut self.rng) % moves.len() as u64) as usize];
+        let last_rank = m.to.rank().index() == 0 || m.to.rank().index() == 7;
+        if last_rank && board.piece_at(m.from).is_some_and(|p| p.0 == PieceKind::Pawn) {
+            let pieces = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
+            m.promotion = Some(pieces[(splitmix64(&mut self.rng) % 4) as usize]);
+        }
+
+        return Some(m);
+    }
+}
+
+impl EngineBackend for RandomPlayer {
+    fn name(&self) -> String { return "emilaa-chess random".to_string(); }
+
+    fn search(&mut self, board: &ChessBoard, _limits: &SearchLimits, _stop: &AtomicBool) -> Option<Move> {
+        return self.choose(board);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::backend::{parse_fen, play_long_algebraic};
+
+    fn position(placement: &str, white_turn: bool) -> ChessBoard {
+        return parse_fen(&[placement, if white_turn { "w" } else { "b" }]).unwrap();
+    }
+
+    fn sq(s: &str) -> Square { return s.parse().unwrap(); }
+
+    /// Score without pruning, to compare alpha-beta with.
+    fn minimax(board: &mut ChessBoard, depth: u32, ply: i32) -> i32 {
+        let moves = legal_moves(board);
+        if moves.is_empty() {
+            let color = if board.get_player() { Color::White } else { Color::Black };
+            return if board.is_in_check(color) { -MATE + ply } else { 0 };
+        }
+        if depth == 0 { return all_captures(board); }
+
+        let mut best = -MATE;
+        for m in moves {
+            let undo = board.make(m).unwrap();
+            best = best.max(-minimax(board, depth - 1, ply + 1));
+            board.unmake(undo);
+        }
+
+        return best;
+    }
+
+    /// Quiescence score without pruning.
+    fn all_captures(board: &mut ChessBoard) -> i32 {
+        let mut best = Standard.eval(board);
+        for m in captures(board) {
+            let undo = board.make(m).unwrap();
+            best = best.max(-all_captures(board));
+            board.unmake(undo);
+        }
+
+        return best;
+    }
+
+    #[test]
+    fn finds_mate_and_material() {
+        // Back rank mate.
+        let board = position("6k1/5ppp/8/8/8/8/8/R5K1", true);
+        let m = best_move(&board, 2).unwrap();
+        assert_eq!((m.from, m.to), (sq("a1"), sq("a8")));
+
+        // Take the free queen.
+        let board = position("3r2k1/8/8/8/8/8/1q6/1R4K1", true);
+        let m = best_move(&board, 2).unwrap();
+        assert_eq!((m.from, m.to), (sq("b1"), sq("b2")));
+
+        // Promote to a queen.
+        let board = position("7k/1P6/8/8/8/8/8/K7", true);
+        assert_eq!(best_move(&board, 1).unwrap().to_string(), "b7b8q");
+    }
+
+    #[test]
+    fn game_over_and_backend() {
+        let board = position("7k/6Q1/6K1/8/8/8/8/8", false);
+        assert_eq!(best_move(&board, 3), None);
+
+        let board = ChessBoard::new();
+        let limits = SearchLimits { depth: Some(1), ..SearchLimits::default() };
+        let m = Engine::default().search(&board, &limits, &AtomicBool::new(false));
+        assert!(m.is_some_and(|m| board.legal_moves().any(|l| l == m)));
+    }
+
+    #[test]
+    fn pruning_keeps_the_score() {
+        let mut board = position("4k3/1p3p2/8/3n4/3P4/2N5/5PP1/4K3", true);
+        let result = search(&board, 3, None);
+        assert_eq!(result.score, minimax(&mut board, 3, 0));
+        assert_eq!(result.depth, 3);
+
+        // The principal variation is a line of legal moves starting with the best move.
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+        for m in result.pv {
+            assert!(board.legal_moves().any(|l| l.from == m.from && l.to == m.to));
+            board.move_by_square(m.from, m.to).unwrap();
+        }
+    }
+
+    #[test]
+    fn deepening_and_budget() {
+        // Qg7 mates, so the search stops deepening after the first iteration.
+        let result = search(&position("7k/8/5K2/8/8/8/8/6Q1", true), 10, None);
+        assert_eq!((result.mate_in(), result.depth), (Some(1), 1));
+
+        // A budget stops the deepening early, with the move of the last finished depth.
+        let result = search(&ChessBoard::new(), MAX_DEPTH, Some(Duration::from_millis(50)));
+        assert!(result.best_move.is_some() && result.depth < MAX_DEPTH);
+    }
+
+    #[test]
+    fn table_between_searches() {
+        let board = position("4k3/1p3p2/8/3n4/3P4/2N5/5PP1/4K3", true);
+        let mut table = TranspositionTable::default();
+        let first = search_with_table(&board, 4, None, &mut table, &Standard);
+        let second = search_with_table(&board, 4, None, &mut table, &Standard);
+        assert_eq!((second.best_move, second.score), (first.best_move, first.score));
+        assert!(second.nodes < first.nodes / 5);
+        assert_eq!(second.pv, first.pv);
+
+        // Mate scores are stored relative to the position, so they stay right when reached at another depth.
+        assert_eq!(from_table(to_table(MATE - 5, 3), 1), MATE - 3);
+        assert_eq!(from_table(to_table(-MATE + 4, 2), 2), -MATE + 4);
+
+        let mut engine = Engine::default();
+        engine.set_option("Hash", "1");
+        assert_eq!(engine.table.capacity(), TranspositionTable::new(1).capacity());
+    }
+
+    #[test]
+    fn no_horizon_blunders() {
+        // Qxd5 wins a pawn, but one half move past the depth cxd5 takes the queen.
+        let board = position("4k3/8/2p5/3p4/8/8/8/3QK3", true);
+        let result = search_with_table(&board, 1, None, &mut TranspositionTable::default(), &eval::Material);
+        assert_ne!(result.best_move.map(|m| m.to), Some(sq("d5")));
+        assert_eq!(result.score, 700);
+
+        // A capture that can't be taken back is still made.
+        let board = position("4k3/8/8/3p4/8/8/8/3QK3", true);
+        assert_eq!(best_move(&board, 1).map(|m| m.to), Some(sq("d5")));
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        /// Likes having the king on the h file.
+        struct KingToH;
+
+        impl Evaluator for KingToH {
+            fn eval(&self, board: &ChessBoard) -> i32 {
+                let color = if board.get_player() { Color::White } else { Color::Black };
+                let on_h = |c: Color| (board.king_square(c).file().index() == 7) as i32;
+                return 100 * (on_h(color) - on_h(color.opposite()));
+            }
+        }
+
+        let board = position("4k3/p7/8/8/8/8/P7/6K1", true);
+        let mut engine = Engine::with_evaluator(KingToH);
+        let limits = SearchLimits { depth: Some(1), ..SearchLimits::default() };
+        assert_eq!(engine.search(&board, &limits, &AtomicBool::new(false)).map(|m| m.to.file().index()), Some(7));
+    }
+
+    #[test]
+    fn multiple_lines() {
+        // Taking the queen is best, then the rook, then the knight.
+        let board = position("7k/2q5/5r2/1N5n/6P1/8/1B6/7K", true);
+        let lines = analyze(&board, 3, 1, None);
+        let targets: Vec<String> = lines.iter().map(|l| l.best_move.unwrap().to.to_string()).collect();
+        assert_eq!(targets, ["c7", "f6", "h5"]);
+        assert!(lines.is_sorted_by(|a, b| a.score >= b.score));
+        assert!(lines.iter().all(|l| l.depth == 1 && l.pv.first() == l.best_move.as_ref()));
+
+        // The first line is what search() finds.
+        let best = search(&board, 1, None);
+        assert_eq!((lines[0].best_move, lines[0].score), (best.best_move, best.score));
+
+        // There are only as many lines as legal moves: Kb1 here, and none once the game has ended.
+        let board = position("7k/8/8/8/8/8/6r1/K7", true);
+        assert_eq!(analyze(&board, 5, 1, None).len(), 1);
+        let board = position("7k/8/8/8/8/8/8/K7", true);
+        assert_eq!(analyze(&board, 5, 1, None).len(), 0);
+    }
+
+    #[test]
+    fn limits() {
+        let board = ChessBoard::new();
+        let result = search_with_limits(&board, &SearchLimits { depth: Some(2), ..SearchLimits::default() });
+        assert_eq!(result.depth, 2);
+
+        // The node limit stops the search between iterations or in the middle of one.
+        let result = search_with_limits(&board, &SearchLimits { nodes: Some(500), ..SearchLimits::default() });
+        assert!(result.nodes <= 501 && result.best_move.is_some());
+
+        let result = search_with_limits(&board, &SearchLimits { movetime: Some(Duration::from_millis(30)), ..SearchLimits::default() });
+        assert!(result.best_move.is_some() && result.depth < MAX_DEPTH);
+
+        let clock = |ms: u64| Some(Duration::from_millis(ms));
+        let mut limits = SearchLimits { time: [clock(60_000), clock(1_500)], ..SearchLimits::default() };
+        assert_eq!(time_budget(&limits, Color::White), clock(2_000));
+        assert_eq!(time_budget(&limits, Color::Black), clock(50));
+        limits.increment = [Duration::from_millis(400); 2];
+        limits.moves_to_go = Some(1);
+        assert_eq!(time_budget(&limits, Color::White), clock(48_000));
+        assert_eq!(time_budget(&limits, Color::Black), clock(1_200));
+        limits.movetime = clock(5);
+        assert_eq!(time_budget(&limits, Color::Black), clock(5));
+        limits.infinite = true;
+        assert_eq!(time_budget(&limits, Color::Black), None);
+    }
+
+    #[test]
+    fn skill_levels() {
+        let board = ChessBoard::new();
+        let limits = SearchLimits::default();
+        let mut engine = Engine::default();
+        engine.set_option("Skill Level", "1");
+        assert_eq!(engine.skill, 1);
+
+        // The weakest level looks one half move ahead and varies its moves, reproducibly for a seed.
+        let mut moves = HashSet::new();
+        for seed in 0..20 {
+            engine.seed(seed);
+            let result = engine.think(&board, &limits, None);
+            engine.seed(seed);
+            assert_eq!(engine.think(&board, &limits, None), result);
+            assert_eq!(result.depth, 1);
+            moves.insert(result.best_move.unwrap());
+        }
+        assert!(moves.len() > 3);
+
+        // At full strength the noise and mistakes are gone.
+        engine.set_option("Skill Level", "10");
+        let best = search(&board, DEFAULT_DEPTH, None).best_move;
+        for seed in 0..3 {
+            engine.seed(seed);
+            assert_eq!(engine.think(&board, &limits, None).best_move, best);
+        }
+
+        // Ended games have no move at any level.
+        engine.skill = 5;
+        assert_eq!(engine.think(&position("7k/5Q2/6K1/8/8/8/8/8", false), &limits, None).best_move, None);
+    }
+
+    #[test]
+    fn random_player() {
+        // Play two games from the same seed, with a promotion to come up.
+        let game = |seed: u64| {
+            let mut board = position("4k3/1P6/8/8/8/8/8/4K3", true);
+            let mut player = RandomPlayer::new(seed);
+            let mut moves = vec![];
+            while let Some(m) = player.choose(&board) {
+                assert!(play_long_algebraic(&mut board, &m.to_string()), "{m}");
+                moves.push(m);
+                if moves.len() == 40 { break; }
+            }
+            return moves;
+        };
+
+        assert_eq!(game(1), game(1));
+        assert_ne!(game(1), game(2));
+        assert!((0..10).map(game).any(|g| g.iter().any(|m| m.promotion.is_some_and(|p| p != PieceKind::Queen))));
+        assert_eq!(RandomPlayer::new(1).choose(&position("7k/5Q2/6K1/8/8/8/8/8", false)), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Static evaluation, scoring a position without searching it.
+
+use crate::{ChessBoard, Color, PieceKind};
+
+/// Centipawns per pseudo-legal move more than the opponent has.
+const MOBILITY_WEIGHT: i32 = 4;
+
+/// Game phase with all pieces but pawns and kings on the board. Knights and bishops count 1, rooks 2 and queens 4.
+const OPENING_PHASE: i32 = 24;
+
+/**
+Bonuses for white pieces by square in the middlegame, indexed by `PieceKind` and then like      <br/>
+`get_board()` (0 is a8). Black uses them mirrored. From Tomasz Michniewski's simplified          <br/>
+evaluation: pieces go to the center, pawns advance but guard the castled king, kings castle.
+*/
+const MIDGAME: [[i32; 64]; 6] = [
+    // Pawn
+    [  0,   0,   0,   0,   0,   0,   0,   0,
+      50,  50,  50,  50,  50,  50,  50,  50,
+      10,  10,  20,  30,  30,  20,  10,  10,
+       5,   5,  10,  25,  25,  10,   5,   5,
+       0,   0,   0,  20,  20,   0,   0,   0,
+       5,  -5, -10,   0,   0, -10,  -5,   5,
+       5,  10,  10, -20, -20,  10,  10,   5,
+       0,   0,   0,   0,   0,   0,   0,   0],
+    // Rook
+    [  0,   0,   0,   0,   0,   0,   0,   0,
+       5,  10,  10,  10,  10,  10,  10,   5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+       0,   0,   0,   5,   5,   0,   0,   0],
+    // Knight
+    [-50, -40, -30, -30, -30, -30, -40, -50,
+     -40, -20,   0,   0,   0,   0, -20, -40,
+     -30,   0,  10,  15,  15,  10,   0, -30,
+     -30,   5,  15,  20,  20,  15,   5, -30,
+     -30,   0,  15,  20,  20,  15,   0, -30,
+     -30,   5,  10,  15,  15,  10,   5, -30,
+     -40, -20,   0,   5,   5,   0, -20, -40,
+     -50, -40, -30, -30, -30, -30, -40, -50],
+    // Bishop
+    [-20, -10, -10, -10, -10, -10, -10, -20,
+     -10,   0,   0,   0,   0,   0,   0, -10,
+     -10,   0,   5,  10,  10,   5,   0, -10,
+     -10,   5,   5,  10,  10,   5,   5, -10,
+     -10,   0,  10,  10,  10,  10,   0, -10,
+     -10,  10,  10,  10,  10,  10,  10, -10,
+     -10,   5,   0,   0,   0,   0,   5, -10,
+     -20, -10, -10, -10, -10, -10, -10, -20],
+    // Queen
+    [-20, -10, -10,  -5,  -5, -10, -10, -20,
+     -10,   0,   0,   0,   0,   0,   0, -10,
+     -10,   0,   5,   5,   5,   5,   0, -10,
+      -5,   0,   5,   5,   5,   5,   0,  -5,
+       0,   0,   5,   5,   5,   5,   0,  -5,
+     -10,   5,   5,   5,   5,   5,   0, -10,
+     -10,   0,   5,   0,   0,   0,   0, -10,
+     -20, -10, -10,  -5,  -5, -10, -10, -20],
+    // King
+    [-30, -40, -40, -50, -50, -40, -40, -30,
+     -30, -40, -40, -50, -50, -40, -40, -30,
+     -30, -40, -40, -50, -50, -40, -40, -30,
+     -30, -40, -40, -50, -50, -40, -40, -30,
+     -20, -30, -30, -40, -40, -30, -30, -20,
+     -10, -20, -20, -20, -20, -20, -20, -10,
+      20,  20,   0,   0,   0,   0,  20,  20,
+      20,  30,  10,   0,   0,  10,  30,  20]
+];
+
+/// Bonuses by square in the endgame, like `MIDGAME`. Pawns run for promotion, rooks go anywhere and the king comes to the center.
+const ENDGAME: [[i32; 64]; 6] = [
+    // Pawn
+    [  0,   0,   0,   0,   0,   0,   0,   0,
+      80,  80,  80,  80,  80,  80,  80,  80,
+      50,  50,  50,  50,  50,  50,  50,  50,
+      30,  30,  30,  30,  30,  30,  30,  30,
+      20,  20,  20,  20,  20,  20,  20,  20,
+      10,  10,  10,  10,  10,  10,  10,  10,
+      10,  10,  10,  10,  10,  10,  10,  10,
+       0,   0,   0,   0,   0,   0,   0,   0],
+    // Rook
+    [0; 64],
+    MIDGAME[PieceKind::Knight as usize],
+    MIDGAME[PieceKind::Bishop as usize],
+    MIDGAME[PieceKind::Queen as usize],
+    // King
+    [-50, -40, -30, -20, -20, -30, -40, -50,
+     -30, -20, -10,   0,   0, -10, -20, -30,
+     -30, -10,  20,  30,  30,  20, -10, -30,
+     -30, -10,  30,  40,  40,  30, -10, -30,
+     -30, -10,  30,  40,  40,  30, -10, -30,
+     -30, -10,  20,  30,  30,  20, -10, -30,
+     -30, -30,   0,   0,   0,   0, -30, -30,
+     -50, -30, -30, -30, -30, -30, -30, -50]
+];
+
+/**
+Scores positions for the search. Implement it to try another evaluation without changing   <br/>
+the search, e.g. `engine::search_with_table(&board, 4, None, &mut table, &MyEval)`.
+*/
+pub trait Evaluator {
+    /**
+    Score a position.                                                                   <br/>
+    Parameters:                                                                         <br/>
+    `board`: Position to score, possibly after `make()` so its legal moves aren't known  <br/>
+    Returns:                                                                            <br/>
+    How good the position is for the side to move in centipawns, negative if it's worse. Scores must stay well within ±900000, which the search keeps for mates.
+    */
+    fn eval(&self, board: &ChessBoard) -> i32;
+}
+
+/// Counts material only.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Material;
+
+impl Evaluator for Material {
+    fn eval(&self, board: &ChessBoard) -> i32 {
+        let color = side_to_move(board);
+        return board.get_board().iter().flatten().map(|&(kind, c)| if c == color { value(kind) } else { -value(kind) }).sum();
+    }
+}
+
+/**
+The default evaluation. Counts material, how many more moves the side to move has than the    <br/>
+opponent, and where the pieces stand. Piece-square bonuses blend from middlegame to endgame    <br/>
+tables as pieces come off the board.
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Standard;
+
+impl Evaluator for Standard {
+    fn eval(&self, board: &ChessBoard) -> i32 {
+        let color = side_to_move(board);
+        let mobility = board.mobility(color) as i32 - board.mobility(color.opposite()) as i32;
+        return Material.eval(board) + MOBILITY_WEIGHT * mobility + piece_squares(board);
+    }
+}
+
+/// Get the piece-square bonuses of the side to move minus the opponent's, tapered by the game phase.
+fn piece_squares(board: &ChessBoard) -> i32 {
+    let color = side_to_move(board);
+    let (mut midgame, mut endgame, mut phase) = (0, 0, 0);
+
+    for (i, (kind, c)) in board.get_board().iter().enumerate().filter_map(|(i, t)| t.map(|p| (i, p))) {
+        // The tables are for white, so black looks them up on the mirrored rank.
+        let square = if c == Color::White { i } else { i ^ 56 };
+        let sign = if c == color { 1 } else { -1 };
+        midgame += sign * MIDGAME[kind as usize][square];
+        endgame += sign * ENDGAME[kind as usize][square];
+        phase += match kind {
+            PieceKind::Knight | PieceKind::Bishop => { 1 }
+            PieceKind::Rook => { 2 }
+            PieceKind::Queen => { 4 }
+            _ => { 0 }
+        };
+    }
+
+    // Promotions can push the phase past the opening.
+    let phase = phase.min(OPENING_PHASE);
+    return (midgame * phase + endgame * (OPENING_PHASE - phase)) / OPENING_PHASE;
+}
+
+/// Get the color of the side to move.
+fn side_to_move(board: &ChessBoard) -> Color {
+    return if board.get_player() { Color::White } else { Color::Black };
+}
+
+/// Get the value of a piece in centipawns.
+pub(super) fn value(kind: PieceKind) -> i32 {
+    return match kind {
+        PieceKind::Pawn => { 100 }
+        PieceKind::Knight => { 320 }
+        PieceKind::Bishop => { 330 }
+        PieceKind::Rook => { 500 }
+        PieceKind::Queen => { 900 }
+        PieceKind::King => { 0 }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::parse_fen;
+
+    #[test]
+    fn evaluators() {
+        let board = ChessBoard::new();
+        assert_eq!((Material.eval(&board), Standard.eval(&board)), (0, 0));
+
+        // Scores are for the side to move: white is a rook up, so black is a rook down.
+        let white = parse_fen(&["4k3/8/8/8/8/8/8/R3K3", "w"]).unwrap();
+        let black = parse_fen(&["4k3/8/8/8/8/8/8/R3K3", "b"]).unwrap();
+        assert_eq!((Material.eval(&white), Material.eval(&black)), (500, -500));
+
+        // The rook and the king have 10 + 5 moves against the black king's 5.
+        assert_eq!(Standard.eval(&white), 500 + MOBILITY_WEIGHT * 10 + piece_squares(&white));
+        assert_eq!(Standard.eval(&black), -Standard.eval(&white));
+    }
+
+    #[test]
+    fn tapered_piece_squares() {
+        let eval = |placement: &str| piece_squares(&parse_fen(&[placement, "w"]).unwrap());
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+
+        // Developing a knight and castling are better in the middlegame.
+        assert!(eval("rnbqkbnr/pppppppp/8/8/8/2N5/PPPPPPPP/R1BQKBNR") > eval(start));
+        assert!(eval("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1") > eval("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQK2R"));
+
+        // With only pawns left, the king belongs in the center.
+        assert!(eval("4k3/pppppppp/8/8/4K3/8/PPPPPPPP/8") > eval("4k3/pppppppp/8/8/8/8/PPPPPPPP/6K1"));
+        assert_eq!(eval("4k3/8/8/8/4K3/8/8/8"), ENDGAME[PieceKind::King as usize][36] - ENDGAME[PieceKind::King as usize][4 ^ 56]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Transposition table, remembering searched positions by their Zobrist hash.
+
+use std::mem::size_of;
+
+use crate::Move;
+
+/// Table size used when none is given.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// How a stored score relates to the real score of the position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub(super) enum Bound {
+    /// The score is exact.
+    Exact,
+    /// A move scored at least this much, so the search was cut off. The real score may be higher.
+    Lower,
+    /// No move scored more than this. The real score may be lower.
+    Upper
+}
+
+/// What a search found out about a position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub(super) struct Entry {
+    /// Zobrist hash of the position, telling positions sharing a slot apart.
+    pub key: u64,
+    /// Depth the position was searched to.
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    /// Best move found, searched first when the position comes up again.
+    pub best: Option<Move>
+}
+
+/**
+Positions already searched, shared between the iterations of a search and between moves   <br/>
+of a game. Positions reached by different move orders are only searched once.
+*/
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>
+}
+
+impl TranspositionTable {
+    /**
+    Get a new empty table.                                                      <br/>
+    Parameters:                                                                 <br/>
+    `size_mb`: Memory to use in megabytes, at least one entry is kept           <br/>
+    Returns:                                                                    <br/>
+    The table.
+    */
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let len = (size_mb * 1024 * 1024 / size_of::<Option<Entry>>()).max(1);
+        return TranspositionTable { entries: vec![None; len] };
+    }
+
+    /// Forget every position, e.g. for a new game.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Get the number of positions the table can hold.
+    pub fn capacity(&self) -> usize { return self.entries.len(); }
+
+    /// Get what is known about a position, if it's stored.
+    pub(super) fn probe(&self, key: u64) -> Option<Entry> {
+        return self.entries[self.slot(key)].filter(|e| e.key == key);
+    }
+
+    /// Store what was found about a position. A deeper search of the same position is kept instead.
+    pub(super) fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+        if self.entries[slot].is_none_or(|old| old.key != entry.key || old.depth <= entry.depth) {
+            self.entries[slot] = Some(entry);
+        }
+    }
+
+    fn slot(&self, key: u64) -> usize { return (key % self.entries.len() as u64) as usize; }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable { return TranspositionTable::new(DEFAULT_SIZE_MB); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: u64, depth: u32) -> Entry {
+        return Entry { key, depth, score: depth as i32, bound: Bound::Exact, best: None };
+    }
+
+    #[test]
+    fn replacement() {
+        let mut table = TranspositionTable::new(0);
+        assert_eq!(table.capacity(), 1);
+
+        table.store(entry(7, 3));
+        table.store(entry(7, 2));
+        assert_eq!(table.probe(7), Some(entry(7, 3)));
+
+        // Another position sharing the slot replaces it.
+        table.store(entry(8, 1));
+        assert_eq!((table.probe(7), table.probe(8)), (None, Some(entry(8, 1))));
+
+        table.clear();
+        assert_eq!(table.probe(8), None);
+        assert!(TranspositionTable::new(1).capacity() > 10_000);
+    }
+}
@@ -1,204 +1,1512 @@
-use std::collections::HashMap;
+#![allow(clippy::needless_return)]
+
+pub mod backend;
+pub mod clock;
+pub mod command;
+pub mod delta;
+pub mod eboard;
+pub mod engine;
+pub mod openings;
+pub mod render;
+pub mod selfplay;
+pub mod tournament;
+pub mod uci;
+pub mod xboard;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
 /// Chess piece structure.
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Piece {
-    id: i8,
-    team: i8,
-    moved: bool,        // Pawns only.
+    kind: PieceKind,
+    color: Color,
     moved_twice: bool   // Pawns only.
 }
 
 impl Piece {
     /// Return new piece.
-    fn new(id: i8, color: i8) -> Piece {
-        if color < -1 || color > 1 { panic!("Bad color..."); }
+    fn new(kind: PieceKind, color: Color) -> Piece {
+        return Piece { kind, color, moved_twice: false };
+    }
+}
+
+/// Column of the board, from A to H.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H
+}
+
+impl File {
+    /// All files, from A to H.
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    /// Get a file from an index 0 ≤ i < 8, where 0 is A.
+    pub fn new(index: usize) -> Option<File> { return Self::ALL.get(index).copied(); }
+
+    /// Get the index of the file, where 0 is A.
+    pub fn index(self) -> usize { return self as usize; }
+}
+
+/// Row of the board, from 1 to 8.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth
+}
+
+impl Rank {
+    /// All ranks, from 1 to 8.
+    pub const ALL: [Rank; 8] = [Rank::First, Rank::Second, Rank::Third, Rank::Fourth,
+                                Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth];
+
+    /// Get a rank from an index 0 ≤ i < 8, where 0 is the first rank.
+    pub fn new(index: usize) -> Option<Rank> { return Self::ALL.get(index).copied(); }
+
+    /// Get the index of the rank, where 0 is the first rank.
+    pub fn index(self) -> usize { return self as usize; }
+}
+
+/// Tile on the board. Index 0 is a8 and 63 is h1, the same as `move_by_index()`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "u8", into = "u8"))]
+pub struct Square(u8);
+
+/// Error returned when parsing a `Square` from a string like "e4" fails.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseSquareError;
+
+impl Square {
+    /// Get a square from an index 0 ≤ i < 64, or `None` if out of range.
+    pub fn new(index: usize) -> Option<Square> {
+        if index > 63 { return None; }
+        return Some(Square(index as u8));
+    }
+
+    /// Get the square on a file and rank.
+    pub fn from_file_rank(file: File, rank: Rank) -> Square {
+        return Self::from_xy((file.index(), 7 - rank.index()));
+    }
+
+    /// Get the index 0 ≤ i < 64 of the square.
+    pub fn index(self) -> usize { return self.0 as usize; }
+
+    /// Get the file of the square.
+    pub fn file(self) -> File { return File::ALL[self.xy().0]; }
+
+    /// Get the rank of the square.
+    pub fn rank(self) -> Rank { return Rank::ALL[7 - self.xy().1]; }
+
+    /// Get a square from (x, y) board indices.
+    fn from_xy(indices: (usize, usize)) -> Square { return Square((indices.1 * 8 + indices.0) as u8); }
+
+    /// Get the (x, y) board indices of the square.
+    fn xy(self) -> (usize, usize) { return ((self.0 % 8) as usize, (self.0 / 8) as usize); }
+}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    /// Parse a square in algebraic notation, e.g. "e4". Case insensitive.
+    fn from_str(s: &str) -> Result<Square, ParseSquareError> {
+        let b = s.as_bytes();
+        if b.len() != 2 { return Err(ParseSquareError); }
+
+        let file = b[0].to_ascii_lowercase();
+        let rank = b[1];
+        if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) { return Err(ParseSquareError); }
+
+        return Ok(Self::from_file_rank(File::ALL[(file - b'a') as usize], Rank::ALL[(rank - b'1') as usize]));
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = ParseSquareError;
+
+    /// Get a square from an index 0 ≤ i < 64.
+    fn try_from(index: u8) -> Result<Square, ParseSquareError> { return Square::new(index as usize).ok_or(ParseSquareError); }
+}
+
+impl From<Square> for u8 {
+    fn from(square: Square) -> u8 { return square.0; }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}{}", (b'a' + self.file().index() as u8) as char, self.rank().index() + 1);
+    }
+}
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "expected a square like \"e4\"");
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+/// Kind of chess piece.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PieceKind {
+    Pawn,
+    Rook,
+    Knight,
+    Bishop,
+    Queen,
+    King
+}
+
+/// Piece a pawn can promote to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PromotionPiece {
+    Queen,
+    Rook,
+    Bishop,
+    Knight
+}
+
+impl From<PromotionPiece> for PieceKind {
+    fn from(piece: PromotionPiece) -> PieceKind {
+        return match piece {
+            PromotionPiece::Queen => { PieceKind::Queen }
+            PromotionPiece::Rook => { PieceKind::Rook }
+            PromotionPiece::Bishop => { PieceKind::Bishop }
+            PromotionPiece::Knight => { PieceKind::Knight }
+        };
+    }
+}
+
+/// Kind of move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveKind {
+    /// Move to an empty tile.
+    Quiet,
+    /// Pawn moving two tiles forward.
+    DoubleStep,
+    /// Pawn capturing a pawn that just moved two tiles.
+    EnPassant,
+    /// Capture on the destination tile.
+    Capture,
+    /// King side castling.
+    KingsideCastle,
+    /// Queen side castling.
+    QueensideCastle
+}
+
+/// Chess move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub kind: MoveKind,
+    /// Piece a pawn promotes to. Promotions are chosen with `promote()` after
+    /// the move, so generated moves leave this as `None`.
+    pub promotion: Option<PieceKind>
+}
+
+impl Move {
+    /// Return new move from (x, y) board indices.
+    fn new(from: (usize, usize), to: (usize, usize), kind: MoveKind) -> Move {
+        return Move { from: Square::from_xy(from), to: Square::from_xy(to), kind, promotion: None };
+    }
+
+    /// Get the square of the piece the move captures, or `None` if it isn't a capture.
+    pub fn captured_square(self) -> Option<Square> {
+        return match self.kind {
+            MoveKind::Capture => { Some(self.to) }
+            MoveKind::EnPassant => { Some(Square::from_file_rank(self.to.file(), self.from.rank())) }
+            _ => { None }
+        };
+    }
+
+    /// Get the squares the rook moves from and to when castling, or `None` for other moves.
+    pub fn rook_squares(self) -> Option<(Square, Square)> {
+        let rank = self.from.rank();
+
+        return match self.kind {
+            MoveKind::KingsideCastle => { Some((Square::from_file_rank(File::H, rank), Square::from_file_rank(File::F, rank))) }
+            MoveKind::QueensideCastle => { Some((Square::from_file_rank(File::A, rank), Square::from_file_rank(File::D, rank))) }
+            _ => { None }
+        };
+    }
+}
+
+impl fmt::Display for Move {
+    /// Write the move in long algebraic notation as used by UCI, e.g. "e2e4" or "e7e8q".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(k) = self.promotion { write!(f, "{}", ChessBoard::letter(k, Color::Black))?; }
+        return Ok(());
+    }
+}
+
+/// Player color.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Color {
+    White,
+    Black
+}
+
+impl Color {
+    /// Get the other color.
+    pub fn opposite(self) -> Color {
+        return match self {
+            Color::White => { Color::Black }
+            Color::Black => { Color::White }
+        };
+    }
+}
+
+/// Why a game ended in a draw.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    /// Neither side has enough pieces left to checkmate.
+    InsufficientMaterial,
+    /// Both players agreed to a draw.
+    Agreement,
+    /// A player ran out of time, but the opponent had nothing left to checkmate with.
+    Timeout
+}
+
+/// State of the game, updated after every move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameStatus {
+    /// The game is in progress.
+    Ongoing,
+    /// The game is in progress and the side to move is in check.
+    Check,
+    /// The game was won by checkmate. Holds the winner.
+    Checkmate(Color),
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+    /// The game ended in a draw.
+    Draw(DrawReason),
+    /// The game was won on time, as the opponent's flag fell. Holds the winner.
+    Timeout(Color)
+}
+
+/// Result of a successful move or promotion.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MoveOutcome {
+    /// The move was played. Holds the game status after it.
+    Played(GameStatus),
+    /// A pawn reached the other side and must be promoted with `promote()` before play continues.
+    PromotionRequired
+}
+
+/// Why a move or promotion was rejected.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MoveError {
+    /// A square is out of range or couldn't be parsed.
+    InvalidSquare,
+    /// There is no piece on the square to move from.
+    NoPieceOnSquare,
+    /// The piece belongs to the side not moving.
+    NotYourTurn,
+    /// The piece can't legally move to the destination.
+    IllegalDestination,
+    /// A pawn must be promoted before the next move.
+    PromotionPending,
+    /// There is no pawn to promote.
+    NoPromotionPending,
+    /// The move doesn't take a pawn to the last rank, or the promotion piece isn't valid.
+    InvalidPromotion,
+    /// The game has already ended.
+    GameOver
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", match self {
+            MoveError::InvalidSquare => { "invalid square" }
+            MoveError::NoPieceOnSquare => { "no piece on the square" }
+            MoveError::NotYourTurn => { "it is the other side's turn" }
+            MoveError::IllegalDestination => { "the piece can't move there" }
+            MoveError::PromotionPending => { "a pawn must be promoted first" }
+            MoveError::NoPromotionPending => { "there is no pawn to promote" }
+            MoveError::InvalidPromotion => { "the move isn't a promotion to a rook, knight, bishop or queen" }
+            MoveError::GameOver => { "the game has ended" }
+        });
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Why a position can't occur in a game.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PositionError {
+    /// A player has no king, or more than one.
+    KingCount(Color),
+    /// A pawn stands on the first or last rank.
+    PawnOnBackRank(Square),
+    /// The player who just moved is in check.
+    OpponentInCheck,
+    /// A castling right is set but the king or rook isn't on its starting square.
+    InvalidCastlingRights,
+    /// The en passant square isn't behind a pawn that could just have moved two tiles.
+    InvalidEnPassant,
+    /// A FEN string can't be read.
+    InvalidFen
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            PositionError::KingCount(c) => { write!(f, "{:?} must have exactly one king", c) }
+            PositionError::PawnOnBackRank(s) => { write!(f, "pawn on the back rank at {}", s) }
+            PositionError::OpponentInCheck => { write!(f, "the side not to move is in check") }
+            PositionError::InvalidCastlingRights => { write!(f, "castling rights don't match the king and rook placement") }
+            PositionError::InvalidEnPassant => { write!(f, "no pawn can have just moved past the en passant square") }
+            PositionError::InvalidFen => { write!(f, "the FEN string can't be read") }
+        };
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+const STRAIGHT: &[PieceKind] = &[PieceKind::Rook, PieceKind::Queen];
+const DIAGONAL: &[PieceKind] = &[PieceKind::Bishop, PieceKind::Queen];
+
+/// Directions a sliding piece can move in, with the kinds of pieces that slide that way.
+const RAYS: [((i8, i8), &[PieceKind]); 8] = [
+    ((1, 0), STRAIGHT), ((0, 1), STRAIGHT), ((-1, 0), STRAIGHT), ((0, -1), STRAIGHT),
+    ((1, 1), DIAGONAL), ((-1, 1), DIAGONAL), ((1, -1), DIAGONAL), ((-1, -1), DIAGONAL)
+];
+
+/// Generated moves, indexed like `move_by_index()` by the tile of the piece that can make them. `None` if not generated.
+type MoveList = [Option<Vec<Move>>; 64];
+
+/// Move list with nothing generated.
+const NO_MOVES: MoveList = [const { None }; 64];
+
+/// Which castling moves each player may still make, if the path is clear.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool
+}
+
+/// Random keys for Zobrist hashing: 768 for pieces on tiles, then side to move, castling rights and en passant files.
+const ZOBRIST: [u64; 781] = zobrist_keys();
+
+/// Generate the Zobrist keys with splitmix64, so they are the same on every platform and run.
+const fn zobrist_keys() -> [u64; 781] {
+    let mut keys = [0u64; 781];
+    let mut state: u64 = 0;
+    let mut i = 0;
+
+    while i < keys.len() {
+        keys[i] = splitmix64(&mut state);
+        i += 1;
+    }
+
+    return keys;
+}
+
+/// Hash a position with 64-bit FNV-1a, for `ChessBoard::state_checksum()` and `delta::BoardView::checksum()`.
+pub(crate) fn position_checksum(squares: &[Option<(PieceKind, Color)>; 64], white_turn: bool, castling: CastlingRights, en_passant: Option<Square>) -> u64 {
+    let mut bytes: Vec<u8> = squares.iter().map(|p| p.map_or(0, |(kind, color)| kind as u8 * 2 + color as u8 + 1)).collect();
+    bytes.extend([white_turn as u8, castling.white_kingside as u8, castling.white_queenside as u8,
+                  castling.black_kingside as u8, castling.black_queenside as u8]);
+    bytes.push(en_passant.map_or(0xff, |s| s.index() as u8));
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    return hash;
+}
+
+/// Advance a splitmix64 generator and get its next number. Small and portable, for keys and engine randomness.
+pub(crate) const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+/// How `ChessBoard::render()` draws the board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct RenderOptions {
+    /// Show file and rank labels.
+    pub coordinates: bool,
+    /// Player whose pieces are drawn at the bottom.
+    pub perspective: Color,
+    /// Use Unicode chess glyphs instead of letters.
+    pub unicode: bool,
+    /// Color the pieces with ANSI escape codes.
+    pub colors: bool
+}
+
+impl Default for RenderOptions {
+    /// Letters with ANSI colors from white's side, like `print()`.
+    fn default() -> Self {
+        return RenderOptions { coordinates: false, perspective: Color::White, unicode: false, colors: true };
+    }
+}
+
+/// Snapshot of a position, used for the undo / redo history.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct State {
+    board: [[Option<Piece>; 8]; 8],
+    status: GameStatus,
+    white_turn: bool,
+    castling: CastlingRights,
+    promoting: bool,
+    promoting_index: (usize, usize),
+    last_move: Option<Move>,
+    captured: [Vec<PieceKind>; 2],
+    halfmove_clock: u32,
+    fullmove_number: u32
+}
+
+/// What `ChessBoard::unmake()` needs to take back a move played with `ChessBoard::make()`.
+pub struct Undo {
+    played: Move,
+    piece: Option<Piece>,
+    captured: Option<Piece>,
+    rook: Option<Piece>,
+    status: GameStatus,
+    castling: CastlingRights,
+    last_move: Option<Move>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    kings: [(usize, usize); 2],
+    attacks: [u64; 2],
+    zobrist: u64,
+    move_list: MoveList
+}
+
+/// What is saved of a `ChessBoard` with serde. The move list is rebuilt when loading.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedBoard {
+    position: State,
+    draw_offer: Option<Color>,
+    history: Vec<State>,
+    future: Vec<State>
+}
+
+/**
+Chess board structure.                                                                          <br/>
+Boards compare and hash equal if they hold the same position: pieces, side to move, castling   <br/>
+rights, en passant square and pending promotion. History, move counters and captured pieces
+are ignored, so a position reached by different move orders is the same key in a `HashMap`.
+*/
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "SavedBoard", into = "SavedBoard"))]
+pub struct ChessBoard {
+    board: [[Option<Piece>; 8]; 8],
+    status: GameStatus,
+    white_turn: bool,
+    castling: CastlingRights,
+    promoting: bool,
+    promoting_index: (usize, usize),
+    last_move: Option<Move>,
+    /// Pieces captured by each player, white first.
+    captured: [Vec<PieceKind>; 2],
+    /// Moves since the last capture or pawn move.
+    halfmove_clock: u32,
+    /// Number of the current move, starting at 1 and increased after black moves.
+    fullmove_number: u32,
+    /// Player with a pending draw offer.
+    draw_offer: Option<Color>,
+    /// King tiles, white first. Updated by `gen_moves()`.
+    kings: [(usize, usize); 2],
+    /// Tiles attacked by each player as bitmasks, white first. Updated by `gen_moves()`.
+    attacks: [u64; 2],
+    /// Zobrist hash of the position, updated move by move.
+    zobrist: u64,
+    move_list: MoveList,
+    /// Pseudo-legal moves of each player's pieces, white first. Only pieces near a changed tile are regenerated.
+    pseudo: [MoveList; 2],
+    /// Tiles changed since each player's pseudo-legal moves were last updated, white first.
+    dirty: [u64; 2],
+    /// Positions before each move, most recent last.
+    history: Vec<State>,
+    /// Positions taken back with `undo()`, most recent last.
+    future: Vec<State>,
+    /// Clocks pressed after every move, if the game is timed.
+    clock: Option<clock::Clock>
+}
+
+impl ChessBoard {
+    /// Get a new board.
+    pub fn new() -> ChessBoard {
+        let mut board = ChessBoard {
+            board: [[None; 8]; 8],
+            status: GameStatus::Ongoing,
+            white_turn: true,
+            castling: CastlingRights { white_kingside: true, white_queenside: true, black_kingside: true, black_queenside: true },
+            promoting: false,
+            promoting_index: (usize::MAX, usize::MAX),
+            last_move: None,
+            captured: [vec![], vec![]],
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            draw_offer: None,
+            kings: [(4, 7), (4, 0)],
+            attacks: [0, 0],
+            zobrist: 0,
+            move_list: NO_MOVES,
+            pseudo: [NO_MOVES, NO_MOVES],
+            dirty: [u64::MAX; 2],
+            history: vec![],
+            future: vec![],
+            clock: None
+        };
+
+        let back_rank: [PieceKind; 8] = [PieceKind::Rook, PieceKind::Knight, PieceKind::Bishop, PieceKind::Queen,
+                                         PieceKind::King, PieceKind::Bishop, PieceKind::Knight, PieceKind::Rook];
+
+        for (i, kind) in back_rank.into_iter().enumerate() {
+            board.board[0][i] = Some(Piece::new(kind, Color::Black));
+            board.board[1][i] = Some(Piece::new(PieceKind::Pawn, Color::Black));
+            board.board[6][i] = Some(Piece::new(PieceKind::Pawn, Color::White));
+            board.board[7][i] = Some(Piece::new(kind, Color::White));
+        }
+
+        board.zobrist = board.compute_zobrist();
+        board.gen_moves();
+
+        return board;
+    }
+
+    /// Reset the board.
+    pub fn reset(&mut self) {
+        *self = ChessBoard::new();
+    }
+
+    /**
+    Take back the last move, including a pending promotion.     <br/>
+    Returns:                                                    <br/>
+    `true` if a move was taken back, otherwise `false`
+    */
+    pub fn undo(&mut self) -> bool {
+        let prev = match self.history.pop() {
+            Some(s) => s,
+            None => { return false; }
+        };
+
+        self.future.push(self.state());
+        self.restore(prev);
+
+        return true;
+    }
+
+    /**
+    Replay the last move taken back with `undo()`.      <br/>
+    Returns:                                            <br/>
+    `true` if a move was replayed, otherwise `false`
+    */
+    pub fn redo(&mut self) -> bool {
+        let next = match self.future.pop() {
+            Some(s) => s,
+            None => { return false; }
+        };
+
+        self.history.push(self.state());
+        self.restore(next);
+        // Replaying the last move of a finished game ends it again.
+        if self.is_game_ended() { self.stop_clock(); }
+
+        return true;
+    }
+
+    /** 
+    Check if "it's joever."                             <br/>
+    Returns:                                            <br/>
+    `true` if the game has ended, otherwise `false`
+    */
+    pub fn is_game_ended(&self) -> bool {
+        return !matches!(self.status, GameStatus::Ongoing | GameStatus::Check);
+    }
+
+    /**
+    Get the state of the game.              <br/>
+    Returns:                                <br/>
+    The `GameStatus` after the last move.
+    */
+    pub fn status(&self) -> GameStatus { return self.status; }
+
+    /**
+    Get the last move played, e.g. to highlight it. Use `captured_square()` and     <br/>
+    `rook_squares()` on it for en passant and castling.                             <br/>
+    Returns:                                                                        <br/>
+    The move, or `None` at the start of the game. Includes the promotion once chosen.
+    */
+    pub fn last_move(&self) -> Option<Move> { return self.last_move; }
+
+    /**
+    Get the pieces a player has captured.                                   <br/>
+    Parameters:                                                             <br/>
+    `color`: Player who captured the pieces                                 <br/>
+    Returns:                                                                <br/>
+    The kinds of the captured pieces, in the order they were taken.
+    */
+    pub fn captured_by(&self, color: Color) -> &[PieceKind] { return &self.captured[color as usize]; }
+
+    /**
+    Get the halfmove clock, the number of moves since the last capture or pawn move.    <br/>
+    Returns:                                                                            <br/>
+    The number of moves by either player.
+    */
+    pub fn halfmove_clock(&self) -> u32 { return self.halfmove_clock; }
+
+    /**
+    Get the fullmove number. It starts at 1 and increases after each move by black.     <br/>
+    Returns:                                                                            <br/>
+    The number of the current move.
+    */
+    pub fn fullmove_number(&self) -> u32 { return self.fullmove_number; }
+
+    /**
+    Get the castling rights. A right is kept until the king or that rook moves, or the rook is captured,   <br/>
+    so it doesn't tell whether castling is legal right now.                                             <br/>
+    Returns:                                                                                            <br/>
+    The castling rights of both players.
+    */
+    pub fn castling_rights(&self) -> CastlingRights { return self.castling; }
+
+    /**
+    Get the en passant target square, the tile a pawn that just moved two tiles passed.     <br/>
+    Returns:                                                                                <br/>
+    The square, or `None` if the last move wasn't a pawn moving two tiles.
+    */
+    pub fn en_passant_square(&self) -> Option<Square> {
+        let m = self.last_move.filter(|m| m.kind == MoveKind::DoubleStep)?;
+        return Some(Square::from_xy((m.to.xy().0, (m.from.xy().1 + m.to.xy().1) / 2)));
+    }
+
+    /**
+    Check if a player's king is attacked.           <br/>
+    Parameters:                                     <br/>
+    `color`: Player to check                        <br/>
+    Returns:                                        <br/>
+    `true` if the king is in check, otherwise `false`
+    */
+    pub fn is_in_check(&self, color: Color) -> bool {
+        let king = self.find_king(color);
+        if king == (usize::MAX, usize::MAX) { return false; }
+
+        return self.attacked(king, color.opposite());
+    }
+
+    /**
+    Check if a square is attacked, without generating any moves.            <br/>
+    Parameters:                                                             <br/>
+    `square`: Square to check                                               <br/>
+    `by`: Player attacking                                                  <br/>
+    Returns:                                                                <br/>
+    `true` if a piece of `by` attacks the square, otherwise `false`
+    */
+    pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        return self.attacked(square.xy(), by);
+    }
+
+    /**
+    Get every square a player attacks, e.g. to show danger squares.        <br/>
+    Parameters:                                                             <br/>
+    `color`: Player attacking                                               <br/>
+    Returns:                                                                <br/>
+    A mask where bit `i` is set if the square with index `i` is attacked, including
+    squares holding the player's own pieces.
+    */
+    pub fn attacked_squares(&self, color: Color) -> u64 { return self.attacks[color as usize]; }
+
+    /**
+    Get the pieces attacking a square.                                      <br/>
+    Parameters:                                                             <br/>
+    `square`: Square to check                                               <br/>
+    Returns:                                                                <br/>
+    The squares of the attacking white pieces and black pieces, in that order.
+    */
+    pub fn attackers_of(&self, square: Square) -> (Vec<Square>, Vec<Square>) {
+        let white = self.attackers(square.xy(), Color::White, false).into_iter().map(Square::from_xy).collect();
+        let black = self.attackers(square.xy(), Color::Black, false).into_iter().map(Square::from_xy).collect();
+
+        return (white, black);
+    }
+
+    /**
+    Check if a move would put the opponent in check, without playing it.   <br/>
+    Parameters:                                                             <br/>
+    `from`: Square to move from                                             <br/>
+    `to`: Square to move to                                                 <br/>
+    Returns:                                                                <br/>
+    `true` if the move is legal and gives check, otherwise `false`. Pawns reaching the
+    last rank are counted as queens.
+    */
+    pub fn gives_check(&self, from: Square, to: Square) -> bool {
+        let m = match self.legal_moves_from(from).into_iter().find(|m| m.to == to) {
+            Some(m) => { m }
+            None => { return false; }
+        };
+
+        let color = match self.board[from.xy().1][from.xy().0] {
+            Some(p) => { p.color }
+            None => { return false; }
+        };
+
+        let king = self.find_king(color.opposite());
+        if king == (usize::MAX, usize::MAX) { return false; }
+
+        return !Self::attackers_on(&self.board_after(m), king, color, true).is_empty();
+    }
+
+    /**
+    Get the moves of the side to move without checking if they leave the king in check.    <br/>
+    Cheaper than `legal_moves()`, and also works after `make()`. Check the moves that are   <br/>
+    actually used with `is_legal()`.                                                         <br/>
+    Returns:                                                                                 <br/>
+    The pseudo-legal moves in the same order as `legal_moves()`, or none while a promotion is pending.
+    */
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let team = self.turn();
+        let mut moves: Vec<Move> = vec![];
+        if self.promoting { return moves; }
+
+        for y in 0..8usize {
+            for x in 0..8usize {
+                if self.board[y][x].is_some_and(|p| p.color == team) { moves.extend(self.gen_piece_moves((x, y), team)); }
+            }
+        }
+
+        return moves;
+    }
+
+    /**
+    Count the pseudo-legal moves of a player, whether or not it's their turn, e.g. for an    <br/>
+    evaluation. Also works after `make()`.                                                    <br/>
+    Parameters:                                                                               <br/>
+    `color`: Player whose moves to count                                                      <br/>
+    Returns:                                                                                  <br/>
+    The number of moves the player's pieces could make, ignoring king safety.
+    */
+    pub fn mobility(&self, color: Color) -> usize {
+        let mut count = 0;
+
+        for y in 0..8usize {
+            for x in 0..8usize {
+                if self.board[y][x].is_some_and(|p| p.color == color) { count += self.gen_piece_moves((x, y), color).len(); }
+            }
+        }
+
+        return count;
+    }
+
+    /**
+    Count the leaf nodes of the legal move tree. Comparing the counts with known ones is the   <br/>
+    standard way to check move generation. Each promotion piece counts as its own move.        <br/>
+    Parameters:                                                                                <br/>
+    `depth`: Number of half moves to look ahead                                                <br/>
+    Returns:                                                                                   <br/>
+    The number of move sequences of that length.
+    */
+    pub fn perft(&self, depth: u32) -> u64 {
+        let mut board = self.clone();
+        return board.perft_from(depth);
+    }
+
+    /// Count leaf nodes with `make()` and `unmake()`.
+    fn perft_from(&mut self, depth: u32) -> u64 {
+        if depth == 0 { return 1; }
+        let mut nodes = 0;
+
+        for m in self.pseudo_legal_moves() {
+            if !self.king_safe_after(m) { continue; }
+
+            let t = m.to.xy();
+            let promotes = self.board[m.from.xy().1][m.from.xy().0].is_some_and(|p| p.kind == PieceKind::Pawn) && (t.1 == 0 || t.1 == 7);
+            let pieces: &[Option<PieceKind>] = if promotes {
+                &[Some(PieceKind::Queen), Some(PieceKind::Rook), Some(PieceKind::Bishop), Some(PieceKind::Knight)]
+            } else {
+                &[None]
+            };
+
+            for piece in pieces {
+                if depth == 1 { nodes += 1; continue; }
+
+                if let Ok(undo) = self.make(Move { promotion: *piece, ..m }) {
+                    nodes += self.perft_from(depth - 1);
+                    self.unmake(undo);
+                }
+            }
+        }
+
+        return nodes;
+    }
+
+    /**
+    Check if a move is legal for the side to move, e.g. one from `pseudo_legal_moves()`.   <br/>
+    Parameters:                                                                             <br/>
+    `m`: Move to check                                                                      <br/>
+    Returns:                                                                                <br/>
+    `true` if the piece can make the move and it doesn't leave its king in check, otherwise `false`
+    */
+    pub fn is_legal(&self, m: Move) -> bool {
+        let team = self.turn();
+        let f = m.from.xy();
+        if self.promoting || !self.board[f.1][f.0].is_some_and(|p| p.color == team) { return false; }
+        if !self.gen_piece_moves(f, team).iter().any(|g| g.to == m.to && g.kind == m.kind) { return false; }
+
+        return self.king_safe_after(m);
+    }
+
+    /// Check if a pseudo-legal move keeps the mover's king out of check.
+    fn king_safe_after(&self, m: Move) -> bool {
+        let f = m.from.xy();
+        let team = match self.board[f.1][f.0] {
+            Some(p) => { p.color }
+            None => { return false; }
+        };
+
+        // The king can't castle out of check or through an attacked tile.
+        if let Some((_, passed)) = m.rook_squares() {
+            if self.attacked(f, team.opposite()) || self.attacked(passed.xy(), team.opposite()) { return false; }
+        }
+
+        let board = self.board_after(m);
+        let king = if self.find_king(team) == f { m.to.xy() } else { self.find_king(team) };
+
+        return Self::attackers_on(&board, king, team.opposite(), true).is_empty();
+    }
+
+    /// Get a copy of the board with a move played. Pawns reaching the last rank promote, to a queen unless the move says otherwise.
+    fn board_after(&self, m: Move) -> [[Option<Piece>; 8]; 8] {
+        let (f, t) = (m.from.xy(), m.to.xy());
+        let mut board = self.board;
+        let mut piece = match board[f.1][f.0].take() {
+            Some(p) => { p }
+            None => { return board; }
+        };
+
+        if piece.kind == PieceKind::Pawn && (t.1 == 0 || t.1 == 7) { piece.kind = m.promotion.unwrap_or(PieceKind::Queen); }
+        if let Some(c) = m.captured_square() { board[c.xy().1][c.xy().0] = None; }
+        if let Some((rf, rt)) = m.rook_squares() { board[rt.xy().1][rt.xy().0] = board[rf.xy().1][rf.xy().0].take(); }
+        board[t.1][t.0] = Some(piece);
+
+        return board;
+    }
+
+    /**
+    Get the pieces pinned to a player's king.                               <br/>
+    Parameters:                                                             <br/>
+    `color`: Player whose pieces to check                                   <br/>
+    Returns:                                                                <br/>
+    Pairs of the pinned piece's square and the square of the enemy piece pinning it.
+    */
+    pub fn pinned_pieces(&self, color: Color) -> Vec<(Square, Square)> {
+        let mut pins: Vec<(Square, Square)> = vec![];
+        let king = self.find_king(color);
+        if king == (usize::MAX, usize::MAX) { return pins; }
+
+        for (k, kinds) in RAYS.iter() {
+            let mut d: (i8, i8) = (king.0 as i8 + k.0, king.1 as i8 + k.1);
+            let mut own: Option<(usize, usize)> = None;
+
+            while self.within_board(d) {
+                let t: (usize, usize) = (d.0 as usize, d.1 as usize);
+
+                match self.board[t.1][t.0] {
+                    None => { }
+                    Some(p) if p.color == color => {
+                        // Two of our own pieces on the ray, neither is pinned.
+                        if own.is_some() { break; }
+                        own = Some(t);
+                    }
+                    Some(p) => {
+                        if let Some(o) = own {
+                            if kinds.contains(&p.kind) { pins.push((Square::from_xy(o), Square::from_xy(t))); }
+                        }
+                        break;
+                    }
+                }
+
+                d = (d.0 + k.0, d.1 + k.1);
+            }
+        }
+
+        return pins;
+    }
+
+    /**
+    Get the tile of a player's king.                                        <br/>
+    Parameters:                                                             <br/>
+    `color`: Player whose king to find                                      <br/>
+    Returns:                                                                <br/>
+    The square of the king.
+    */
+    pub fn king_square(&self, color: Color) -> Square { return Square::from_xy(self.find_king(color)); }
+
+    /**
+    Get the enemy pieces giving check to the side to move.                  <br/>
+    Returns:                                                                <br/>
+    The squares of the checking pieces. Empty if not in check, and at most two.
+    */
+    pub fn checkers(&self) -> Vec<Square> {
+        let color = if self.white_turn { Color::White } else { Color::Black };
+        let king = self.find_king(color);
+        if king == (usize::MAX, usize::MAX) { return vec![]; }
+
+        return self.attackers(king, color.opposite(), false).into_iter().map(Square::from_xy).collect();
+    }
+
+    /**
+    Check that the position could occur in a game: each player has one king, no pawns stand on   <br/>
+    the first or last rank, the player who just moved isn't in check and the castling rights    <br/>
+    match the kings and rooks. A pawn waiting for promotion is allowed on the last rank.         <br/>
+    Returns:                                                                                     <br/>
+    `Ok(())`, or the first problem found.
+    */
+    pub fn validate_position(&self) -> Result<(), PositionError> {
+        for color in [Color::White, Color::Black] {
+            let kings = self.board.iter().flatten().flatten().filter(|p| p.kind == PieceKind::King && p.color == color).count();
+            if kings != 1 { return Err(PositionError::KingCount(color)); }
+        }
+
+        for y in [0usize, 7] {
+            for x in 0..8usize {
+                if self.board[y][x].is_some_and(|p| p.kind == PieceKind::Pawn) && !(self.promoting && self.promoting_index == (x, y)) {
+                    return Err(PositionError::PawnOnBackRank(Square::from_xy((x, y))));
+                }
+            }
+        }
+
+        // While a promotion is pending the player who moved is still the one to move.
+        if !self.promoting && self.is_in_check(self.turn().opposite()) { return Err(PositionError::OpponentInCheck); }
+
+        let at = |x: usize, y: usize, kind: PieceKind, color: Color| self.board[y][x].is_some_and(|p| p.kind == kind && p.color == color);
+        let rights = [
+            (self.castling.white_kingside, 7, 7, Color::White), (self.castling.white_queenside, 0, 7, Color::White),
+            (self.castling.black_kingside, 7, 0, Color::Black), (self.castling.black_queenside, 0, 0, Color::Black)
+        ];
+
+        for (right, rook_x, y, color) in rights {
+            if right && !(at(4, y, PieceKind::King, color) && at(rook_x, y, PieceKind::Rook, color)) {
+                return Err(PositionError::InvalidCastlingRights);
+            }
+        }
+
+        return Ok(());
+    }
+
+    /**
+    Set up a board from a FEN string, e.g. from a GUI or a puzzle collection. The game starts      <br/>
+    from there with no history. Fields after the piece placement may be left out, which means     <br/>
+    white to move, no castling rights, no en passant square, and the counters at 0 and 1.         <br/>
+    Parameters:                                                                                   <br/>
+    `fen`: FEN string, e.g. "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"         <br/>
+    Returns:                                                                                      <br/>
+    The board, or why the string or its position is invalid.
+    */
+    pub fn from_fen(fen: &str) -> Result<ChessBoard, PositionError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.is_empty() || fields.len() > 6 { return Err(PositionError::InvalidFen); }
+
+        let mut squares = [None; 64];
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 8 { return Err(PositionError::InvalidFen); }
+
+        for (y, row) in rows.iter().enumerate() {
+            let mut x = 0;
+            for c in row.chars() {
+                if let Some(skip) = c.to_digit(10) { x += skip as usize; continue; }
+                if x > 7 { return Err(PositionError::InvalidFen); }
+
+                let kind = match c.to_ascii_lowercase() {
+                    'p' => { PieceKind::Pawn }
+                    'r' => { PieceKind::Rook }
+                    'n' => { PieceKind::Knight }
+                    'b' => { PieceKind::Bishop }
+                    'q' => { PieceKind::Queen }
+                    'k' => { PieceKind::King }
+                    _ => { return Err(PositionError::InvalidFen); }
+                };
+                squares[y * 8 + x] = Some((kind, if c.is_ascii_uppercase() { Color::White } else { Color::Black }));
+                x += 1;
+            }
+            if x != 8 { return Err(PositionError::InvalidFen); }
+        }
+
+        let turn = match fields.get(1).copied() {
+            Some("w") | None => { Color::White }
+            Some("b") => { Color::Black }
+            _ => { return Err(PositionError::InvalidFen); }
+        };
+
+        let castling = fields.get(2).copied().unwrap_or("-");
+        if castling != "-" && (castling.is_empty() || !castling.chars().all(|c| "KQkq".contains(c))) { return Err(PositionError::InvalidFen); }
+        let rights = CastlingRights {
+            white_kingside: castling.contains('K'),
+            white_queenside: castling.contains('Q'),
+            black_kingside: castling.contains('k'),
+            black_queenside: castling.contains('q')
+        };
+
+        let mut board = ChessBoard::try_from((squares, turn, rights))?;
+
+        if let Some(square) = fields.get(3).filter(|f| **f != "-") {
+            board.set_en_passant(square.parse().map_err(|_| PositionError::InvalidFen)?)?;
+        }
+
+        let counter = |i: usize, default: u32| fields.get(i).map_or(Ok(default), |f| f.parse::<u32>().map_err(|_| PositionError::InvalidFen));
+        board.halfmove_clock = counter(4, 0)?;
+        board.fullmove_number = counter(5, 1)?;
+        if board.fullmove_number == 0 { return Err(PositionError::InvalidFen); }
+
+        return Ok(board);
+    }
+
+    /**
+    Get the position as a FEN string, which `from_fen()` reads back to the same board.    <br/>
+    Returns:                                                                               <br/>
+    The FEN string, e.g. "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    */
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for (y, row) in self.board.iter().enumerate() {
+            let mut empty = 0;
+            for tile in row {
+                match tile {
+                    Some(p) => {
+                        if empty > 0 { fen.push_str(&empty.to_string()); }
+                        empty = 0;
+                        fen.push(Self::letter(p.kind, p.color));
+                    }
+                    None => { empty += 1; }
+                }
+            }
+            if empty > 0 { fen.push_str(&empty.to_string()); }
+            if y < 7 { fen.push('/'); }
+        }
+
+        let rights = [
+            (self.castling.white_kingside, 'K'), (self.castling.white_queenside, 'Q'),
+            (self.castling.black_kingside, 'k'), (self.castling.black_queenside, 'q')
+        ];
+        let castling: String = rights.iter().filter(|r| r.0).map(|r| r.1).collect();
+        let en_passant = self.en_passant_square().map_or("-".to_string(), |s| s.to_string());
+
+        return format!("{} {} {} {} {} {}", fen, if self.turn() == Color::White { 'w' } else { 'b' },
+            if castling.is_empty() { "-" } else { &castling }, en_passant, self.halfmove_clock, self.fullmove_number);
+    }
+
+    /**
+    Check if a pawn can be promoted.                           <br/>
+    Returns:                                                    <br/>
+    `true` if the a pawn can be promoted, otherwise `false`
+    */
+    pub fn can_promote(&self) -> bool { return self.promoting; }
+
+    /**
+    Get the team that is playing.                   <br/>
+    Returns:                                        <br/>
+    `true` if white is playing, otherwise `false`
+    */
+    pub fn get_player(&self) -> bool { return self.white_turn; }
+
+    /**
+    Try to promote a pawn.                                      <br/>
+    Parameters:                                                 <br/>
+    `piece`: Piece to promote to                                <br/>
+    Returns:                                                    <br/>
+    The game status after the promotion, or `MoveError::NoPromotionPending`.
+    */
+    pub fn promote(&mut self, piece: PromotionPiece) -> Result<MoveOutcome, MoveError> {
+        if !self.promoting { return Err(MoveError::NoPromotionPending); }
+        let kind = PieceKind::from(piece);
+
+        let (x, y) = self.promoting_index;
+        let p = match self.board.get_mut(y).and_then(|r| r.get_mut(x)).and_then(|t| t.as_mut()) {
+            Some(p) => { p }
+            None => { return Err(MoveError::NoPromotionPending); }
+        };
+
+        self.zobrist ^= Self::piece_key(*p, (x, y));
+        p.kind = kind;
+        self.zobrist ^= Self::piece_key(*p, (x, y));
+        let tile: u64 = 1 << (y * 8 + x);
+        self.dirty = [self.dirty[0] | tile, self.dirty[1] | tile];
+        if let Some(m) = &mut self.last_move { m.promotion = Some(kind); }
+        self.promoting = false;
+        self.promoting_index = (usize::MAX, usize::MAX);
+        self.white_turn = !self.white_turn;
+        self.zobrist ^= ZOBRIST[768];
+        self.update_status();
+        self.press_clock();
 
-        return Piece { id: id, team: color, moved: false, moved_twice: false };
+        return Ok(MoveOutcome::Played(self.status));
     }
 
-    /// Get a white piece.
-    fn white(id: i8) -> Piece {
-        if id < 1 || id > 6 { panic!("Bad piece..."); }
-        return Self::new(id, -1);
+    /**
+    Get a copy of the board.                                                                <br/>
+    Returns:                                                                                <br/>
+    A flat array with size 64, indexed like `move_by_index()`. Each tile holds the kind
+    and color of its piece, or `None` if it is empty.
+    */
+    pub fn get_board(&self) -> [Option<(PieceKind, Color)>; 64] {
+        let mut b: [Option<(PieceKind, Color)>; 64] = [None; 64];
+
+        for y in 0..8usize {
+            for x in 0..8usize {
+                b[y*8+x] = self.board[y][x].map(|p| (p.kind, p.color));
+            }            
+        }
+
+        return b;
     }
 
-    /// Get a black piece.
-    fn black(id: i8) -> Piece {
-        if id < 1 || id > 6 { panic!("Bad piece..."); }
-        return Self::new(id, 1);
+    /**
+    Get a copy of the board as seen by a player.                                            <br/>
+    Parameters:                                                                             <br/>
+    `color`: Player whose pieces are at the bottom                                          <br/>
+    Returns:                                                                                <br/>
+    The same array as `get_board()` for white. For black it is rotated, so index 0 is h1
+    and 63 is a8.
+    */
+    pub fn get_board_oriented(&self, color: Color) -> [Option<(PieceKind, Color)>; 64] {
+        let mut b = self.get_board();
+        if color == Color::Black { b.reverse(); }
+
+        return b;
     }
 
-    /// Get an empty / dummy piece.
-    fn empty() -> Piece {
-        return Self::new(0, 0);
+    /**
+    Get the piece on a square.                                              <br/>
+    Parameters:                                                             <br/>
+    `square`: Square to look at                                             <br/>
+    Returns:                                                                <br/>
+    The kind and color of the piece, or `None` if the square is empty.
+    */
+    pub fn piece_at(&self, square: Square) -> Option<(PieceKind, Color)> {
+        let (x, y) = square.xy();
+        return self.board[y][x].map(|p| (p.kind, p.color));
     }
-}
 
-#[derive(PartialEq, Clone, Copy)]
-enum Flags {
-    None = 0,
-    TwoSteps,
-    EnPassant,
-    Capture,
-    Kastling,
-    Qastling
-}
+    /**
+    Get the occupied tiles, e.g. to compare with an electronic board.       <br/>
+    Returns:                                                                <br/>
+    A bitmask where bit `i` is set if the tile with index `i` holds a piece.
+    */
+    pub fn occupancy(&self) -> u64 {
+        let mut mask = 0u64;
 
-/// Chess board structure.
-pub struct ChessBoard {
-    board: [[Piece; 8]; 8],
-    game_ended: bool,
-    white_turn: bool,
-    /// White castling, king side.
-    wkcr: bool,
-    /// White castling, queen side.
-    wqcr: bool,
-    /// Black castling, king side.
-    bkcr: bool,
-    /// Black castling, queen side.
-    bqcr: bool,
-    promoting: bool,
-    promoting_index: (usize, usize),
-    move_list: HashMap<(usize, usize), Vec<(usize, usize, Flags)>>
-}
+        for y in 0..8usize {
+            for x in 0..8usize {
+                if self.board[y][x].is_some() { mask |= 1 << (y*8+x); }
+            }
+        }
 
-impl ChessBoard {
-    /// Get a new board.
-    pub fn new() -> ChessBoard {
-        let mut board = ChessBoard {
-            board: [[Piece::empty(); 8]; 8],
-            game_ended: false,
-            white_turn: true,
-            wkcr: true,
-            wqcr: true,
-            bkcr: true,
-            bqcr: true,
-            promoting: false,
-            promoting_index: (usize::MAX, usize::MAX),
-            move_list: HashMap::new()
-        };
+        return mask;
+    }
+
+    /**
+    Get the legal moves of the piece on a square. They are generated on demand for just     <br/>
+    that piece, so this is also up to date after `make()`.                                  <br/>
+    Parameters:                                                                             <br/>
+    `square`: Square of the piece                                                           <br/>
+    Returns:                                                                                <br/>
+    The moves, or an empty list if the square is empty, holds a piece of
+    the side not moving, or a promotion is pending.
+    */
+    pub fn legal_moves_from(&self, square: Square) -> Vec<Move> {
+        let team = self.turn();
+        let xy = square.xy();
+        if self.promoting || self.is_game_ended() || !self.board[xy.1][xy.0].is_some_and(|p| p.color == team) { return vec![]; }
+
+        return self.gen_piece_moves(xy, team).into_iter().filter(|m| self.king_safe_after(*m)).collect();
+    }
+
+    /**
+    Get the legal destinations of the piece on a square as a bitmask, e.g. to mark      <br/>
+    drop targets in a GUI. Like `legal_moves_from()`, it also works after `make()`.     <br/>
+    Parameters:                                                                         <br/>
+    `from`: Square of the piece                                                         <br/>
+    Returns:                                                                            <br/>
+    A mask where bit `i` is set if the piece can move to the square with index `i`. Zero in the
+    same cases `legal_moves_from()` is empty.
+    */
+    pub fn legal_targets_mask(&self, from: Square) -> u64 {
+        return self.legal_moves_from(from).iter().fold(0, |mask, m| mask | 1 << m.to.index());
+    }
 
-        board.board[0][0] = Piece::black(2);
-        board.board[0][1] = Piece::black(3);
-        board.board[0][2] = Piece::black(4);
-        board.board[0][3] = Piece::black(5);
-        board.board[0][4] = Piece::black(6);
-        board.board[0][5] = Piece::black(4);
-        board.board[0][6] = Piece::black(3);
-        board.board[0][7] = Piece::black(2);
+    /**
+    Get all legal moves of the side to move. The order is stable: by the index of the      <br/>
+    square moved from, then of the square moved to, as in `move_by_index()` (a8 first).    <br/>
+    Returns:                                                                               <br/>
+    An iterator over the moves. It is empty while a promotion is pending
+    and after the game has ended.
+    */
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        let done = self.promoting || self.is_game_ended();
+
+        return self.move_list.iter().filter(move |_| !done).flatten().flatten().copied();
+    }
 
-        board.board[7][0] = Piece::white(2);
-        board.board[7][1] = Piece::white(3);
-        board.board[7][2] = Piece::white(4);
-        board.board[7][3] = Piece::white(5);
-        board.board[7][4] = Piece::white(6);
-        board.board[7][5] = Piece::white(4);
-        board.board[7][6] = Piece::white(3);
-        board.board[7][7] = Piece::white(2);
+    /**
+    Get the game as JSON for web front ends. The schema is stable:                                  <br/>
+    `board`: 64 tiles indexed like `get_board()`, each `null` or `{"kind": "knight", "color": "white"}`  <br/>
+    `turn`: `"white"` or `"black"`                                                                  <br/>
+    `status`: `{"state": s}` where `s` is `"ongoing"`, `"check"`, `"checkmate"` or `"timeout"` with `"winner"`,
+    `"stalemate"` or `"draw"` with `"reason"` (`"insufficient_material"`, `"agreement"` or `"timeout"`)  <br/>
+    `promotion_pending`: `true` while `promote()` must be called                                    <br/>
+    `legal_moves`: Object from square names to lists of destination square names                   <br/>
+    `history`: Moves played so far, e.g. `"e2e4"` or `"e7e8q"`                                      <br/>
+    Returns:                                                                                        <br/>
+    The JSON document.
+    */
+    pub fn to_json(&self) -> String {
+        let name = |v: &dyn fmt::Debug| format!("\"{}\"", format!("{:?}", v).to_lowercase());
+
+        let board: Vec<String> = self.get_board().iter().map(|p| match p {
+            Some((k, c)) => { format!("{{\"kind\":{},\"color\":{}}}", name(k), name(c)) }
+            None => { "null".to_string() }
+        }).collect();
+
+        let status = match self.status {
+            GameStatus::Checkmate(c) => { format!("{{\"state\":\"checkmate\",\"winner\":{}}}", name(&c)) }
+            GameStatus::Timeout(c) => { format!("{{\"state\":\"timeout\",\"winner\":{}}}", name(&c)) }
+            GameStatus::Draw(DrawReason::InsufficientMaterial) => { "{\"state\":\"draw\",\"reason\":\"insufficient_material\"}".to_string() }
+            GameStatus::Draw(r) => { format!("{{\"state\":\"draw\",\"reason\":{}}}", name(&r)) }
+            s => { format!("{{\"state\":{}}}", name(&s)) }
+        };
 
-        for i in 0..8usize {
-            board.board[1][i] = Piece::black(1);
-            board.board[6][i] = Piece::white(1);
+        let mut moves: Vec<String> = vec![];
+        for i in 0..64usize {
+            let from = Square(i as u8);
+            let mut targets: Vec<Square> = self.legal_moves_from(from).iter().map(|m| m.to).collect();
+            targets.sort();
+            let targets: Vec<String> = targets.iter().map(|t| format!("\"{}\"", t)).collect();
+            if !targets.is_empty() { moves.push(format!("\"{}\":[{}]", from, targets.join(","))); }
         }
 
-        board.gen_moves();
+        // Each snapshot holds the move before it, and the board holds the latest.
+        let history: Vec<String> = self.history.iter().skip(1).map(|s| s.last_move).chain([self.last_move])
+            .flatten()
+            .map(|m| format!("\"{}\"", m))
+            .collect();
 
-        return board;
+        return format!("{{\"board\":[{}],\"turn\":{},\"status\":{},\"promotion_pending\":{},\"legal_moves\":{{{}}},\"history\":[{}]}}",
+            board.join(","), name(&self.turn()), status, self.promoting, moves.join(","), history.join(","));
     }
 
-    /// Reset the board.
-    pub fn reset(&mut self) {
-        self.board = ChessBoard::new().board;
-        self.game_ended = false;
-        self.white_turn = true;
-        self.wkcr = true;
-        self.wqcr = true;
-        self.bkcr = true;
-        self.bqcr = true;
-        self.promoting = false;
-        self.promoting_index = (usize::MAX, usize::MAX);
-        self.move_list = HashMap::new();
+    /**
+    Get the Zobrist hash of the position, e.g. for transposition tables. It is updated with    <br/>
+    each move instead of being computed from scratch.                                         <br/>
+    Returns:                                                                                  <br/>
+    A 64-bit hash of the pieces, side to move, castling rights and en passant square.
+    */
+    pub fn zobrist_hash(&self) -> u64 { return self.zobrist; }
+
+    /**
+    Get the named opening the game is in, e.g. to show "B90 Sicilian Defence: Najdorf Variation"  <br/>
+    next to a game. Positions are matched rather than moves, so transpositions are recognized.    <br/>
+    Returns:                                                                                     <br/>
+    The last opening from `openings::OPENINGS` the game passed through, or `None` if it left      <br/>
+    the book before any.
+    */
+    pub fn current_opening(&self) -> Option<openings::Opening> {
+        let current = std::iter::once(self.placement_hash());
+        let earlier = self.history.iter().rev().map(|s| Self::placement_key(&s.board, s.white_turn));
+        return current.chain(earlier).find_map(openings::by_position);
     }
 
-    /** 
-    Check if "it's joever."                             <br/>
-    Returns:                                            <br/>
-    `true` if the game has ended, otherwise `false`
+    /**
+    Get a checksum of the position. It doesn't depend on the platform or the run, so both  <br/>
+    sides of a network game can compare it after every move to detect desyncs. It is the   <br/>
+    same as `delta::BoardView::checksum()` of the position.                                <br/>
+    Returns:                                                                               <br/>
+    A 64-bit FNV-1a hash of the pieces, side to move, castling rights and en passant square. <br/>
+    A pending promotion shows as the pawn on the last rank. The moves that led to the position don't change it.
     */
-    pub fn is_game_ended(&self) -> bool { return self.game_ended; }
+    pub fn state_checksum(&self) -> u64 {
+        return position_checksum(&self.get_board(), self.white_turn, self.castling, self.en_passant_square());
+    }
 
     /**
-    Check if a pawn can be promoted.                            <br/>
+    Offer a draw to the opponent. Making a move declines it.    <br/>
+    Parameters:                                                 <br/>
+    `color`: Player offering the draw                           <br/>
     Returns:                                                    <br/>
-    `true` if the a pawn can be promoted, otherwise `false`
+    `true` if the offer was made, otherwise `false`
     */
-    pub fn can_promote(&self) -> bool { return self.promoting; }
+    pub fn offer_draw(&mut self, color: Color) -> bool {
+        if self.is_game_ended() || self.draw_offer.is_some() { return false; }
+
+        self.draw_offer = Some(color);
+        return true;
+    }
 
     /**
-    Get the team that is playing.                   <br/>
-    Returns:                                        <br/>
-    `true` if white is playing, otherwise `false`
+    Accept the pending draw offer, ending the game.     <br/>
+    Returns:                                            <br/>
+    `true` if the game was drawn, otherwise `false`
     */
-    pub fn get_player(&self) -> bool { return self.white_turn; }
+    pub fn accept_draw(&mut self) -> bool {
+        if self.is_game_ended() || self.draw_offer.is_none() { return false; }
+
+        self.draw_offer = None;
+        self.status = GameStatus::Draw(DrawReason::Agreement);
+        self.stop_clock();
+        return true;
+    }
 
     /**
-    Try to promote a pawn.                              <br/>
+    Decline the pending draw offer.                     <br/>
     Returns:                                            <br/>
-    `true` if a pawn got promoted, otherwise `false`.
+    `true` if an offer was declined, otherwise `false`
     */
-    pub fn promote(&mut self, id: i8) -> bool {
-        if self.promoting && id < 6 && id > 1 {
-            self.board[self.promoting_index.1][self.promoting_index.0].id = id;
-            self.promoting = false;
-            self.promoting_index = (usize::MAX, usize::MAX);
-            self.white_turn = !self.white_turn;
-            if self.gen_moves() { self.game_ended = true; }
-            return true;
-        }
-        
-        return  false;
+    pub fn decline_draw(&mut self) -> bool {
+        return self.draw_offer.take().is_some();
     }
 
     /**
-    Get a copy of the board.                                                            <br/>
-    Returns:                                                                            <br/>
-    A flat array of tuples with size 64. First element is the piece id, second is color.
+    Time the game with a chess clock, e.g. `Clock::new(Duration::from_secs(300), Bonus::Fischer(Duration::from_secs(3)))`.  <br/>
+    Each move stops the mover's clock, adds the bonus and starts the opponent's, so the clock   <br/>
+    first runs after the first move. It stops when the game ends. Taking moves back doesn't      <br/>
+    change it, `reset()` removes it and it isn't saved with serde.                               <br/>
+    Parameters:                                                                                  <br/>
+    `clock`: Clock to use, or `None` for an untimed game
     */
-    pub fn get_board(&self) -> [(i8, i8); 64] {
-        let mut b: [(i8, i8); 64] = [(0,0); 64];
+    pub fn set_clock(&mut self, clock: Option<clock::Clock>) { self.clock = clock; }
 
-        for y in 0..8usize {
-            for x in 0..8usize {
-                b[y*8+x] = (self.board[y][x].id, self.board[y][x].team);
-            }            
-        }
+    /// Get the clock of the game, e.g. to check `flag()`, or `None` if the game is untimed.
+    pub fn clock(&self) -> Option<&clock::Clock> { return self.clock.as_ref(); }
 
-        return b;
+    /**
+    Get the time left on a player's clock, counting down while it runs.     <br/>
+    Parameters:                                                             <br/>
+    `color`: Player whose clock to read                                     <br/>
+    Returns:                                                                <br/>
+    The time left, or `None` if the game is untimed.
+    */
+    pub fn time_remaining(&self, color: Color) -> Option<Duration> { return self.clock.map(|c| c.time_remaining(color)); }
+
+    /**
+    End the game if a flag fell on its clock. It is also checked before every move, but a game     <br/>
+    that is waiting for a move needs this to end on time.                                         <br/>
+    Returns:                                                                                      <br/>
+    `true` if the game ended on time: `GameStatus::Timeout` with the opponent as winner, or a     <br/>
+    draw with `DrawReason::Timeout` if the opponent has no mating material. Otherwise `false`.
+    */
+    pub fn check_flag(&mut self) -> bool {
+        if self.is_game_ended() { return false; }
+        let Some(loser) = self.clock.and_then(|c| c.flag()) else { return false; };
+
+        let winner = loser.opposite();
+        self.status = if self.has_mating_material(winner) { GameStatus::Timeout(winner) } else { GameStatus::Draw(DrawReason::Timeout) };
+        self.stop_clock();
+        return true;
+    }
+
+    /**
+    Check if a player has the pieces to checkmate with, e.g. to tell a loss on time from a draw.  <br/>
+    Parameters:                                                                                 <br/>
+    `color`: Player to check                                                                    <br/>
+    Returns:                                                                                    <br/>
+    `false` if the player has only the king, or the king and a single knight or bishop, otherwise `true`
+    */
+    pub fn has_mating_material(&self, color: Color) -> bool {
+        let pieces: Vec<PieceKind> = self.board.iter().flatten().flatten().filter(|p| p.color == color && p.kind != PieceKind::King).map(|p| p.kind).collect();
+        return !matches!(pieces[..], [] | [PieceKind::Knight] | [PieceKind::Bishop]);
     }
 
+    /**
+    Get the player with a pending draw offer.   <br/>
+    Returns:                                    <br/>
+    `Some(color)` if a draw is offered, otherwise `None`
+    */
+    pub fn draw_offer(&self) -> Option<Color> { return self.draw_offer; }
+
     /** Move piece by algebraic notation.                          <br/>
     Parameters:                                                    <br/>
     `from`: File from A to H and rank from 1 to 8. Example: "b1"   <br/>
-    `to`: File from A to H and rank from 1 to 8. Example: "a3"     <br/>
+    `to`: File from A to H and rank from 1 to 8, optionally followed by the piece a pawn promotes to. Example: "a3", "e8q"  <br/>
     Returns:                                                       <br/>
-    `true` on success, otherwise `false`
+    What happened after the move, or why it was rejected.
     */
-    pub fn move_by_algebraic(&mut self, from: &str, to: &str) -> bool {
-        if from.is_empty() || from.len() > 2 || to.is_empty() || to.len() > 2 { return false; }
+    pub fn move_by_algebraic(&mut self, from: &str, to: &str) -> Result<MoveOutcome, MoveError> {
+        if to.len() == 3 && to.is_char_boundary(2) {
+            let piece = match to.as_bytes()[2].to_ascii_lowercase() {
+                b'q' => { PromotionPiece::Queen }
+                b'r' => { PromotionPiece::Rook }
+                b'b' => { PromotionPiece::Bishop }
+                b'n' => { PromotionPiece::Knight }
+                _ => { return Err(MoveError::InvalidPromotion); }
+            };
+
+            return match (from.parse::<Square>(), to[..2].parse::<Square>()) {
+                (Ok(f), Ok(t)) => { self.move_with_promotion(f, t, piece) }
+                _ => { Err(MoveError::InvalidSquare) }
+            };
+        }
 
-        let file_f = from.as_bytes()[0].to_ascii_lowercase() as i8;
-        let rank_f = from.as_bytes()[1].to_ascii_lowercase() as i8;
-        let file_t = to.as_bytes()[0].to_ascii_lowercase() as i8;
-        let rank_t = to.as_bytes()[1].to_ascii_lowercase() as i8;
+        return match (from.parse::<Square>(), to.parse::<Square>()) {
+            (Ok(f), Ok(t)) => { self.move_by_square(f, t) }
+            _ => { Err(MoveError::InvalidSquare) }
+        };
+    }
 
-        if file_f < 97 || file_f > 104 || rank_f < 49 || rank_f > 56 { return false; }
-        if file_t < 97 || file_t > 104 || rank_t < 49 || rank_t > 56 { return false; }
+    /** Move a pawn to the last rank and promote it in one step.   <br/>
+    Parameters:                                                    <br/>
+    `from`: Square to move from                                    <br/>
+    `to`: Square to move to                                        <br/>
+    `piece`: Piece to promote to                                   <br/>
+    Returns:                                                       <br/>
+    What happened after the move, or why it was rejected. Moves that don't promote a pawn are rejected
+    with `MoveError::InvalidPromotion`.
+    */
+    pub fn move_with_promotion(&mut self, from: Square, to: Square, piece: PromotionPiece) -> Result<MoveOutcome, MoveError> {
+        let last_rank = to.rank() == Rank::First || to.rank() == Rank::Eighth;
+        if self.piece_at(from).is_some_and(|p| p.0 != PieceKind::Pawn || !last_rank) { return Err(MoveError::InvalidPromotion); }
 
-        let from_: i8 = file_f - 97 + (rank_f - 56).abs() * 8;
-        let to_: i8 = file_t - 97 + (rank_t - 56).abs() * 8;
+        return match self.move_by_square(from, to)? {
+            MoveOutcome::PromotionRequired => { self.promote(piece) }
+            outcome => { Ok(outcome) }
+        };
+    }
 
-        return self.move_by_index(from_ as usize, to_ as usize);
+    /** Move piece by square.           <br/>
+    Parameters:                         <br/>
+    `from`: Square to move from         <br/>
+    `to`: Square to move to             <br/>
+    Returns:                            <br/>
+    What happened after the move, or why it was rejected.
+    */
+    pub fn move_by_square(&mut self, from: Square, to: Square) -> Result<MoveOutcome, MoveError> {
+        return self.move_by_index(from.index(), to.index());
     }
 
     /** Move piece by index.                <br/>
@@ -206,351 +1514,622 @@ impl ChessBoard {
     `from`: Index to move from 0 ≤ i < 64   <br/>
     `to`: Index to move from 0 ≤ i < 64     <br/>
     Returns:                                <br/>
-    `true` on success, otherwise `false`
+    What happened after the move, or why it was rejected.
     */
-    pub fn move_by_index(&mut self, from: usize, to: usize) -> bool {
-        if from > 63 || to > 63 || from == to { return false; }
-        if self.promoting { return false; }
-        let from_: (usize, usize) = ((from as i8 % 8) as usize, ((from as i8 - from as i8 % 8) / 8) as usize);
-        let to_: (usize, usize) = ((to as i8 % 8) as usize, ((to as i8 - to as i8 % 8) / 8) as usize);
+    pub fn move_by_index(&mut self, from: usize, to: usize) -> Result<MoveOutcome, MoveError> {
+        if from > 63 || to > 63 { return Err(MoveError::InvalidSquare); }
+        self.check_flag();
+        if self.is_game_ended() { return Err(MoveError::GameOver); }
+        if self.promoting { return Err(MoveError::PromotionPending); }
+        let from_: (usize, usize) = (from % 8, from / 8);
+        let to_: (usize, usize) = (to % 8, to / 8);
+
+        let piece = match self.board[from_.1][from_.0] {
+            Some(p) => { p }
+            None => { return Err(MoveError::NoPieceOnSquare); }
+        };
+
+        if piece.color != self.turn() { return Err(MoveError::NotYourTurn); }
 
-        if self.board[from_.1][from_.0].team == -1 && !self.white_turn { return false; }
-        if self.board[from_.1][from_.0].team ==  1 &&  self.white_turn { return false; }
+        let played = match self.move_list[from].iter().flatten().find(|m| m.to == Square::from_xy(to_)) {
+            Some(m) => *m,
+            None => { return Err(MoveError::IllegalDestination); }
+        };
 
-        let get = self.move_list.get(&from_);
-        let moves: &Vec<(usize, usize, Flags)>;
+        self.history.push(self.state());
+        self.future.clear();
 
-        if get.is_some() {
-            moves = get.unwrap();
-        } else {
-            return false;
+        // Moving declines a draw offered by the opponent.
+        if self.draw_offer.is_some_and(|c| c != piece.color) { self.draw_offer = None; }
+
+        self.apply_move(played);
+
+        // Has a pawn reached the other side?
+        if piece.kind == PieceKind::Pawn && (to_.1 == 0 || to_.1 == 7) {
+            self.promoting = true;
+            self.promoting_index = to_;
+            return Ok(MoveOutcome::PromotionRequired);
         }
 
-        let mut move_type: Flags = Flags::None;
-        let mut found: bool = false;
-        for m in moves.iter() {
-            if m.0 == to_.0 && m.1 == to_.1 {
-                found = true;
-                move_type = m.2;
-                break;
-            }
+        self.white_turn = !self.white_turn;
+        self.zobrist ^= ZOBRIST[768];
+        self.update_status();
+        self.press_clock();
+        
+        return Ok(MoveOutcome::Played(self.status));
+    }
+
+    /**
+    Play a move for search, e.g. alpha-beta. Unlike `move_by_index()`, the move isn't added to     <br/>
+    the history and the legal moves and status aren't generated for the new position, so           <br/>
+    `legal_moves()` is empty until `unmake()` or `refresh()`. The move is only checked so far as    <br/>
+    the board stays playable, e.g. it can't capture a king. A promotion is played right away, to   <br/>
+    a queen unless the move says otherwise.                                                         <br/>
+    Parameters:                                                                                     <br/>
+    `m`: Legal move in the position, e.g. from `legal_moves()`                                      <br/>
+    Returns:                                                                                        <br/>
+    What `unmake()` needs to take the move back, or why the move can't be played.
+    */
+    pub fn make(&mut self, m: Move) -> Result<Undo, MoveError> {
+        if self.promoting { return Err(MoveError::PromotionPending); }
+        let color = match self.piece_at(m.from) {
+            Some(p) => { p.1 }
+            None => { return Err(MoveError::NoPieceOnSquare); }
+        };
+        if color != self.turn() { return Err(MoveError::NotYourTurn); }
+
+        let empty = |s: Square| self.piece_at(s).is_none();
+        let enemy = |s: Square| self.piece_at(s).is_some_and(|p| p.1 != color && p.0 != PieceKind::King);
+        let fits = match m.kind {
+            MoveKind::Capture => { enemy(m.to) }
+            MoveKind::EnPassant => { empty(m.to) && m.captured_square().is_some_and(enemy) }
+            _ => { empty(m.to) && m.rook_squares().is_none_or(|(_, r)| empty(r)) }
+        };
+        if !fits { return Err(MoveError::IllegalDestination); }
+
+        let from = m.from.xy();
+        let undo = Undo {
+            played: m,
+            piece: self.board[from.1][from.0],
+            captured: m.captured_square().and_then(|c| self.board[c.xy().1][c.xy().0]),
+            rook: m.rook_squares().and_then(|(r, _)| self.board[r.xy().1][r.xy().0]),
+            status: self.status,
+            castling: self.castling,
+            last_move: self.last_move,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            kings: self.kings,
+            attacks: self.attacks,
+            zobrist: self.zobrist,
+            move_list: std::mem::replace(&mut self.move_list, NO_MOVES)
+        };
+
+        self.apply_move(m);
+
+        let to = m.to.xy();
+        if let Some(p) = self.board[to.1][to.0].as_mut().filter(|p| p.kind == PieceKind::Pawn && (to.1 == 0 || to.1 == 7)) {
+            self.zobrist ^= Self::piece_key(*p, to);
+            p.kind = m.promotion.unwrap_or(PieceKind::Queen);
+            self.zobrist ^= Self::piece_key(*p, to);
+            self.last_move = Some(Move { promotion: Some(p.kind), ..m });
         }
 
-        if !found { return false; }
+        self.white_turn = !self.white_turn;
+        self.zobrist ^= ZOBRIST[768];
+
+        return Ok(undo);
+    }
+
+    /**
+    Take back a move played with `make()`, restoring the legal moves and status as they were.   <br/>
+    Parameters:                                                                                 <br/>
+    `undo`: What `make()` returned for the last move played
+    */
+    pub fn unmake(&mut self, undo: Undo) {
+        let m = undo.played;
+        let (from, to) = (m.from.xy(), m.to.xy());
+
+        self.board[to.1][to.0] = None;
+        self.board[from.1][from.0] = undo.piece;
 
-        if move_type == Flags::Capture { self.board[to_.1][to_.0] = Piece::empty(); }
-        if move_type == Flags::TwoSteps { self.board[from_.1][from_.0].moved_twice = true; }
-        if move_type == Flags::EnPassant {
-            let team = self.board[from_.1][from_.0].team;
-            let ep = (to_.0, (to_.1 as i8 - team) as usize);
-            self.board[ep.1][ep.0] = Piece::empty();
+        if let Some((rook_from, rook_to)) = m.rook_squares() {
+            self.board[rook_to.xy().1][rook_to.xy().0] = None;
+            self.board[rook_from.xy().1][rook_from.xy().0] = undo.rook;
         }
 
-        if !self.board[from_.1][from_.0].moved { 
-            self.board[from_.1][from_.0].moved = true;
+        if let (Some(c), Some(at)) = (undo.captured, m.captured_square()) {
+            self.board[at.xy().1][at.xy().0] = Some(c);
+            self.captured[c.color.opposite() as usize].pop();
+        }
 
-            if self.board[from_.1][from_.0].id == 2 {
-                if self.board[from_.1][from_.0].team == -1 {
-                    if from_.0 == 0 { self.wqcr = false; }
-                    if from_.0 == 7 { self.wkcr = false; }
-                } else {
-                    if from_.0 == 0 { self.bqcr = false; }
-                    if from_.0 == 7 { self.bkcr = false; }
-                }
-            }
+        // Moving cleared the en passant flag of the pawn that could be taken before.
+        if let Some(lm) = undo.last_move.filter(|lm| lm.kind == MoveKind::DoubleStep) {
+            if let Some(p) = &mut self.board[lm.to.xy().1][lm.to.xy().0] { p.moved_twice = true; }
+        }
 
-            if self.board[from_.1][from_.0].id == 6 && (move_type != Flags::Kastling && move_type != Flags::Qastling) {
-                if self.board[from_.1][from_.0].team == -1 {
-                    self.wqcr = false;
-                    self.wkcr = false;
-                } else {
-                    self.bqcr = false;
-                    self.bkcr = false;
-                }
+        let touched = Self::touched(m, undo.last_move);
+        self.dirty = [self.dirty[0] | touched, self.dirty[1] | touched];
+
+        self.white_turn = !self.white_turn;
+        self.status = undo.status;
+        self.castling = undo.castling;
+        self.last_move = undo.last_move;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.kings = undo.kings;
+        self.attacks = undo.attacks;
+        self.zobrist = undo.zobrist;
+        self.move_list = undo.move_list;
+    }
+
+    /// Generate the legal moves and status after `make()`, which skips that. Other moves do it themselves.
+    pub fn refresh(&mut self) {
+        self.update_status();
+    }
+
+    /// Play a legal move on the board, without switching turns or generating moves. A promotion is left pending.
+    fn apply_move(&mut self, played: Move) {
+        let from_ = played.from.xy();
+        let to_ = played.to.xy();
+        let move_type = played.kind;
+        let mut piece = match self.board[from_.1][from_.0] {
+            Some(p) => { p }
+            None => { return; }
+        };
+
+        let old_en_passant = self.en_passant_square();
+        let old_castling = self.castling;
+        let touched = Self::touched(played, self.last_move);
+        self.dirty = [self.dirty[0] | touched, self.dirty[1] | touched];
+        self.last_move = Some(played);
+
+        let captured = played.captured_square().and_then(|c| self.board[c.xy().1][c.xy().0].map(|p| (p, c.xy())));
+        if let Some((c, _)) = captured {
+            self.captured[piece.color as usize].push(c.kind);
+        }
+        self.zobrist ^= Self::piece_key(piece, from_);
+
+        if piece.kind == PieceKind::Pawn || played.captured_square().is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if piece.color == Color::Black { self.fullmove_number += 1; }
+
+        // The pawn taken en passant stands beside the moving pawn.
+        if move_type == MoveKind::EnPassant { self.board[from_.1][to_.0] = None; }
+
+        // Only the pawn that just moved two tiles can be taken en passant.
+        for p in self.board.iter_mut().flatten().flatten() { p.moved_twice = false; }
+        piece.moved_twice = move_type == MoveKind::DoubleStep;
+
+        // Moving a rook from its starting tile, or capturing it there, loses that side.
+        for t in [from_, to_] {
+            match t {
+                (0, 7) => { self.castling.white_queenside = false; }
+                (7, 7) => { self.castling.white_kingside = false; }
+                (0, 0) => { self.castling.black_queenside = false; }
+                (7, 0) => { self.castling.black_kingside = false; }
+                _ => { }
             }
         }
-        
-        if self.board[from_.1][from_.0].moved_twice && move_type != Flags::TwoSteps { self.board[from_.1][from_.0].moved_twice = false; }
-
-        // Handle castling.
-        if move_type == Flags::Kastling {
-            if self.wkcr && self.board[from_.1][from_.0].team == -1 {
-                let mut tmp = self.board[from_.1][from_.0];
-                self.board[from_.1][from_.0] = self.board[to_.1][to_.0];
-                self.board[to_.1][to_.0] = tmp;
-                tmp = self.board[7][7];
-                self.board[7][7] = self.board[7][5];
-                self.board[7][5] = tmp;
-                self.board[7][5].moved = true;
-
-                self.wkcr = false;
-                self.wqcr = false;
+
+        if piece.kind == PieceKind::King {
+            if piece.color == Color::White {
+                self.castling.white_queenside = false;
+                self.castling.white_kingside = false;
+            } else {
+                self.castling.black_queenside = false;
+                self.castling.black_kingside = false;
             }
+        }
 
-            if self.bkcr && self.board[from_.1][from_.0].team == 1 {
-                let mut tmp = self.board[from_.1][from_.0];
-                self.board[from_.1][from_.0] = self.board[to_.1][to_.0];
-                self.board[to_.1][to_.0] = tmp;
-                tmp = self.board[0][7];
-                self.board[0][7] = self.board[0][5];
-                self.board[0][5] = tmp;
-                self.board[0][5].moved = true;
-
-                self.bkcr = false;
-                self.bqcr = false;
+        self.board[from_.1][from_.0] = None;
+        self.board[to_.1][to_.0] = Some(piece);
+        if piece.kind == PieceKind::King { self.kings[piece.color as usize] = to_; }
+        self.zobrist ^= Self::piece_key(piece, to_);
+        if let Some((c, at)) = captured { self.zobrist ^= Self::piece_key(c, at); }
+
+        // Handle castling, the rook jumps to the other side of the king.
+        if move_type == MoveKind::KingsideCastle || move_type == MoveKind::QueensideCastle {
+            let (rook_from, rook_to) = if move_type == MoveKind::KingsideCastle { (7, 5) } else { (0, 3) };
+
+            if let Some(rook) = self.board[from_.1][rook_from].take() {
+                self.board[from_.1][rook_to] = Some(rook);
+                self.zobrist ^= Self::piece_key(rook, (rook_from, from_.1)) ^ Self::piece_key(rook, (rook_to, from_.1));
             }
-        } else if move_type == Flags::Qastling {
-            if self.wqcr && self.board[from_.1][from_.0].team == -1 {
-                let mut tmp = self.board[from_.1][from_.0];
-                self.board[from_.1][from_.0] = self.board[to_.1][to_.0];
-                self.board[to_.1][to_.0] = tmp;
-                tmp = self.board[7][0];
-                self.board[7][0] = self.board[7][3];
-                self.board[7][3] = tmp;
-                self.board[7][3].moved = true;
-
-                self.wkcr = false;
-                self.wqcr = false;
+        }
+
+        self.zobrist ^= Self::castling_key(old_castling) ^ Self::castling_key(self.castling);
+        self.zobrist ^= Self::en_passant_key(old_en_passant) ^ Self::en_passant_key(self.en_passant_square());
+    }
+
+    /**
+    Explain why a move is or isn't legal. Meant for debugging and bug reports.  <br/>
+    Parameters:                                                                 <br/>
+    `from`: Index to move from 0 ≤ i < 64                                       <br/>
+    `to`: Index to move to 0 ≤ i < 64                                           <br/>
+    Returns:                                                                    <br/>
+    A readable trace. If the move leaves the king in check, one line per enemy
+    piece that could capture it, along with the tiles it would pass.
+    */
+    pub fn explain_move(&mut self, from: usize, to: usize) -> String {
+        if from > 63 || to > 63 { return "Index out of range.".to_string(); }
+        if self.promoting { return "A pawn must be promoted first.".to_string(); }
+
+        let from_: (usize, usize) = (from % 8, from / 8);
+        let to_: (usize, usize) = (to % 8, to / 8);
+        let team = self.turn();
+        let p0 = match self.board[from_.1][from_.0] {
+            Some(p) => { p }
+            None => { return format!("No piece on {}.", Square::from_xy(from_)); }
+        };
+
+        if p0.color != team {
+            return format!("{} on {} can't move, it's the other team's turn.", Self::piece_name(p0.kind), Square::from_xy(from_));
+        }
+
+        let m = match self.gen_piece_moves(from_, team).into_iter().find(|m| m.to == Square::from_xy(to_)) {
+            Some(m) => { m }
+            None => { return format!("{} on {} can't reach {}.", Self::piece_name(p0.kind), Square::from_xy(from_), Square::from_xy(to_)); }
+        };
+
+        if let Some((_, passed)) = m.rook_squares() {
+            if self.attacked(from_, team.opposite()) || self.attacked(passed.xy(), team.opposite()) {
+                return format!("King on {} can't castle out of or through check.", Square::from_xy(from_));
             }
+        }
 
-            if self.bqcr && self.board[from_.1][from_.0].team == 1 {
-                let mut tmp = self.board[from_.1][from_.0];
-                self.board[from_.1][from_.0] = self.board[to_.1][to_.0];
-                self.board[to_.1][to_.0] = tmp;
-                tmp = self.board[0][0];
-                self.board[0][0] = self.board[0][3];
-                self.board[0][3] = tmp;
-                self.board[0][3].moved = true;
-
-                self.bkcr = false;
-                self.bqcr = false;
+        // Play the move the same way validate_moves() does.
+        let ki = if p0.kind == PieceKind::King { to_ } else { self.find_king(team) };
+        let before = self.board;
+        self.board = self.board_after(m);
+
+        let mut lines: Vec<String> = vec![];
+        for y in 0..8usize {
+            for x in 0..8usize {
+                let enemy = match self.board[y][x] {
+                    Some(p) if p.color != team => { p }
+                    _ => { continue; }
+                };
+
+                if self.gen_piece_moves((x, y), enemy.color).iter().any(|em| em.to == Square::from_xy(ki)) {
+                    let ray: Vec<String> = Self::ray((x, y), ki, enemy.kind).into_iter().map(|t| Square::from_xy(t).to_string()).collect();
+                    lines.push(format!("{} on {} would capture the king on {} along {}.",
+                        Self::piece_name(enemy.kind), Square::from_xy((x, y)), Square::from_xy(ki), ray.join(", ")));
+                }
             }
-        } else {
-            let tmp = self.board[from_.1][from_.0];
-            self.board[from_.1][from_.0] = self.board[to_.1][to_.0];
-            self.board[to_.1][to_.0] = tmp;
         }
 
-        // Has a pawn reached the other side?
-        if self.board[to_.1][to_.0].id == 1 && ((self.board[to_.1][to_.0].team == -1 && to_.1 == 0) || (self.board[to_.1][to_.0].team == 1 && to_.1 == 7))
-        {
-            self.promoting = true;
-            self.promoting_index = to_;
-            return true;
+        self.board = before;
+
+        if lines.is_empty() {
+            return format!("{} on {} to {} is legal.", Self::piece_name(p0.kind), Square::from_xy(from_), Square::from_xy(to_));
         }
 
-        self.white_turn = !self.white_turn;
-        if self.gen_moves() { self.game_ended = true; }
-        
-        return true;
+        return lines.join("\n");
     }
+
     /**
     Generate moves for current team.                                            <br/>
     Returns:                                                                    <br/>
-    `true` if movelist is empty, equivalent to a checkmate, otherwise `false`
+    `true` if movelist is empty (checkmate or stalemate), otherwise `false`
     */
     fn gen_moves(&mut self) -> bool {
-        self.move_list.clear();
+        self.kings = [self.scan_king(Color::White), self.scan_king(Color::Black)];
+        self.attacks = [self.attack_map(Color::White), self.attack_map(Color::Black)];
 
-        let team: i8 = if self.white_turn { -1 } else { 1 };
-        let mut team_indices: Vec<(usize, usize)> = vec![];
+        let team = self.turn();
+        let changed = std::mem::take(&mut self.dirty[team as usize]);
+        let mut pseudo = std::mem::replace(&mut self.pseudo[team as usize], NO_MOVES);
 
-        for y in 0..8usize {
-            for x in 0..8usize {
-                if self.board[y][x].team == team { team_indices.push((x,y)); }
-            }
-        }
+        for (i, moves) in pseudo.iter_mut().enumerate() {
+            let xy = (i % 8, i / 8);
+            if !self.board[xy.1][xy.0].is_some_and(|p| p.color == team) { *moves = None; continue; }
 
-        // This should not happen.
-        if team_indices.is_empty() { 
-            self.game_ended = true; 
-            panic!("No pieces in team. This should not happen...");    
+            // Keep the moves of pieces that nothing near has changed for.
+            if moves.is_none() || self.depends_on(xy, changed) { *moves = Some(self.gen_piece_moves(xy, team)); }
         }
 
-        for i in team_indices.iter() {
-            let current_index: (i8, i8) = (i.0 as i8, i.1 as i8);
-            let mut moves: Vec<(usize, usize, Flags)> = vec![];
-            
-            match self.board[i.1][i.0].id {
-                1 => { moves.append(&mut self.gen_pawn_move(current_index, team)); }
-                2 => { moves.append(&mut self.gen_rook_move(current_index, team)); }
-                3 => { moves.append(&mut self.gen_knight_move(current_index, team)); }
-                4 => { moves.append(&mut self.gen_bishop_move(current_index, team)); }
-                5 => { moves.append(&mut self.gen_queen_move(current_index, team)); }
-                6 => { moves.append(&mut self.gen_king_move(current_index, team)); }
+        self.move_list = pseudo.clone();
+        self.pseudo[team as usize] = pseudo;
+        self.validate_moves();
 
-                _ => { }
+        return self.move_list.iter().flatten().all(|moves| moves.is_empty());
+    }
+
+    /// Check if the pseudo-legal moves of a piece may have changed with the changed tiles.
+    fn depends_on(&self, indices: (usize, usize), changed: u64) -> bool {
+        const KNIGHT: [(i8, i8); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+        let (x, y) = (indices.0 as i8, indices.1 as i8);
+        let hit = |d: (i8, i8)| self.within_board(d) && changed & 1 << (d.1 * 8 + d.0) != 0;
+
+        let piece = match self.board[indices.1][indices.0] {
+            Some(p) if !hit((x, y)) => { p }
+            _ => { return true; }
+        };
+
+        return match piece.kind {
+            // Steps, captures and the pawns beside it that could be taken en passant.
+            PieceKind::Pawn => {
+                let dir: i8 = if piece.color == Color::White { -1 } else { 1 };
+                (-1..=1).any(|dx| hit((x + dx, y)) || hit((x + dx, y + dir)) || hit((x + dx, y + 2 * dir)))
+            }
+            PieceKind::Knight => { KNIGHT.iter().any(|k| hit((x + k.0, y + k.1))) }
+            // Castling depends on the whole back rank.
+            PieceKind::King => { changed & 0xff << (y * 8) != 0 || (-1..=1).any(|dx| (-1..=1).any(|dy| hit((x + dx, y + dy)))) }
+            _ => {
+                RAYS.iter().filter(|r| r.1.contains(&piece.kind)).any(|(k, _)| {
+                    let mut d: (i8, i8) = (x + k.0, y + k.1);
+
+                    // Up to and including the first piece in the way.
+                    while self.within_board(d) {
+                        if hit(d) { return true; }
+                        if !self.empty_tile((d.0 as usize, d.1 as usize)) { break; }
+                        d = (d.0 + k.0, d.1 + k.1);
+                    }
+
+                    return false;
+                })
             }
+        };
+    }
 
-            self.move_list.insert(i.to_owned(), moves);
-        }
+    /// Get the tiles a move changes, including the pawn that could be taken en passant before it.
+    fn touched(m: Move, previous: Option<Move>) -> u64 {
+        let mut mask: u64 = 1 << m.from.index() | 1 << m.to.index();
+        if let Some(c) = m.captured_square() { mask |= 1 << c.index(); }
+        if let Some((rf, rt)) = m.rook_squares() { mask |= 1 << rf.index() | 1 << rt.index(); }
+        if let Some(p) = previous.filter(|p| p.kind == MoveKind::DoubleStep) { mask |= 1 << p.to.index(); }
+
+        return mask;
+    }
 
-        self.validate_moves(team);
+    /// Remove the generated moves that leave the king in check.
+    fn validate_moves(&mut self) {
+        let mut move_list = std::mem::replace(&mut self.move_list, NO_MOVES);
+        for moves in move_list.iter_mut().flatten() { moves.retain(|m| self.king_safe_after(*m)); }
+        self.move_list = move_list;
+    }
 
-        return self.move_list.is_empty();
+    /// Check if the side to move is in check and set the game status accordingly.
+    fn update_status(&mut self) {
+        let team = self.turn();
+        let no_moves = self.gen_moves();
+        let check = self.is_in_check(team);
+
+        if no_moves && check {
+            self.status = GameStatus::Checkmate(team.opposite());
+        } else if no_moves {
+            self.status = GameStatus::Stalemate;
+        } else if self.insufficient_material() {
+            self.status = GameStatus::Draw(DrawReason::InsufficientMaterial);
+        } else if check {
+            self.status = GameStatus::Check;
+        } else {
+            self.status = GameStatus::Ongoing;
+        }
     }
 
-    /// Validate generated moves.
-    /// TODO:
-    /// Fix to use indices.
-    fn validate_moves(&mut self, team: i8) {
-        let mut bad_moves: Vec<(usize, usize, usize)> = vec![];
-        let mut king_indices: (usize, usize) = (usize::MAX, usize::MAX);
+    /**
+    Check if neither side can checkmate: only kings remain, plus a single knight or     <br/>
+    bishop, or any number of bishops that all stand on the same tile color.
+    */
+    fn insufficient_material(&self) -> bool {
+        let mut minors: Vec<(usize, usize, PieceKind)> = vec![];
 
         for y in 0..8usize {
             for x in 0..8usize {
-                if self.board[y][x].team == team && self.board[y][x].id == 6 { 
-                    king_indices = (x, y);
-                    break;
+                match self.board[y][x].map(|p| p.kind) {
+                    None | Some(PieceKind::King) => { }
+                    Some(k @ (PieceKind::Knight | PieceKind::Bishop)) => { minors.push((x, y, k)); }
+                    _ => { return false; }
+                }
+            }
+        }
+
+        if minors.len() <= 1 { return true; }
+
+        let shade = (minors[0].0 + minors[0].1) % 2;
+        return minors.iter().all(|m| m.2 == PieceKind::Bishop && (m.0 + m.1) % 2 == shade);
+    }
+
+    /// Get the tiles a team attacks as a bitmask, whether they hold a piece or not.
+    fn attack_map(&self, team: Color) -> u64 {
+        const KNIGHT: [(i8, i8); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+        const KING: [(i8, i8); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (1, 1), (0, 1), (-1, 1)];
+        let dir: i8 = if team == Color::White { -1 } else { 1 };
+        let mut mask = 0u64;
+
+        for y in 0..8i8 {
+            for x in 0..8i8 {
+                let p = match self.board[y as usize][x as usize] {
+                    Some(p) if p.color == team => { p }
+                    _ => { continue; }
+                };
+
+                let mut targets: Vec<(i8, i8)> = vec![];
+                match p.kind {
+                    PieceKind::Pawn => { targets.extend([(x - 1, y + dir), (x + 1, y + dir)]); }
+                    PieceKind::Knight => { targets.extend(KNIGHT.iter().map(|k| (x + k.0, y + k.1))); }
+                    PieceKind::King => { targets.extend(KING.iter().map(|k| (x + k.0, y + k.1))); }
+                    _ => {
+                        for (k, _) in RAYS.iter().filter(|r| r.1.contains(&p.kind)) {
+                            let mut d: (i8, i8) = (x + k.0, y + k.1);
+
+                            // Sliders attack up to and including the first piece in the way.
+                            while self.within_board(d) {
+                                targets.push(d);
+                                if !self.empty_tile((d.0 as usize, d.1 as usize)) { break; }
+                                d = (d.0 + k.0, d.1 + k.1);
+                            }
+                        }
+                    }
                 }
+
+                for t in targets.into_iter().filter(|t| self.within_board(*t)) { mask |= 1 << (t.1 * 8 + t.0); }
             }
         }
 
-        if king_indices == (usize::MAX, usize::MAX) {
-            panic!("This shouldn't happen...");
+        return mask;
+    }
+
+    /// Get the cached king tile of a team. Returns `(usize::MAX, usize::MAX)` if there is none.
+    fn find_king(&self, team: Color) -> (usize, usize) { return self.kings[team as usize]; }
+
+    /// Search the board for the king of a team. Returns `(usize::MAX, usize::MAX)` if there is none.
+    fn scan_king(&self, team: Color) -> (usize, usize) {
+        for y in 0..8usize {
+            for x in 0..8usize {
+                if self.board[y][x].is_some_and(|p| p.color == team && p.kind == PieceKind::King) { return (x, y); }
+            }
         }
 
-        for k in self.move_list.iter() {
-            let v = k.1;
+        return (usize::MAX, usize::MAX);
+    }
+
+    /// Check if any piece of a team attacks a tile.
+    fn attacked(&self, indices: (usize, usize), team: Color) -> bool {
+        return !self.attackers(indices, team, true).is_empty();
+    }
+
+    /// Find the pieces of a team attacking a tile, looking outwards from the tile.
+    /// With `first_only`, stop at the first attacker found.
+    fn attackers(&self, indices: (usize, usize), team: Color, first_only: bool) -> Vec<(usize, usize)> {
+        return Self::attackers_on(&self.board, indices, team, first_only);
+    }
 
-            for (index, m) in v.iter().enumerate() {
-                let p0 = self.board[k.0.1][k.0.0];
-                let p1 = self.board[m.1][m.0];
-                let mut ki = king_indices;
+    /// Find the pieces of a team attacking a tile on any board, e.g. a copy with a move played.
+    fn attackers_on(board: &[[Option<Piece>; 8]; 8], indices: (usize, usize), team: Color, first_only: bool) -> Vec<(usize, usize)> {
+        const PAWN: &[PieceKind] = &[PieceKind::Pawn];
+        const KNIGHT: &[PieceKind] = &[PieceKind::Knight];
+        const KING: &[PieceKind] = &[PieceKind::King];
 
-                if p0.id == 6 { ki = (m.0, m.1); }
-                
-                // Swap
-                if m.2 == Flags::Capture { self.board[m.1][m.0] = Piece::empty() }
-                let tmp = self.board[m.1][m.0];
-                self.board[m.1][m.0] = self.board[k.0.1][k.0.0];
-                self.board[k.0.1][k.0.0] = tmp;
+        let inside = |d: (i8, i8)| d.0 < 8 && d.0 > -1 && d.1 < 8 && d.1 > -1;
+        let t: (i8, i8) = (indices.0 as i8, indices.1 as i8);
+        let mut candidates: Vec<((i8, i8), &[PieceKind])> = vec![];
 
-                // Enemy tries to kill the king.
-                // Get moves on new board.
-                let mut enemy_moves: HashMap<(usize, usize), Vec<(usize, usize, Flags)>> = HashMap::new();
-                let mut enemy_indices: Vec<(usize, usize)> = vec![];
+        // Pawns attack diagonally forward, so look one row back from their point of view.
+        let dir: i8 = if team == Color::White { 1 } else { -1 };
+        candidates.push(((t.0 - 1, t.1 + dir), PAWN));
+        candidates.push(((t.0 + 1, t.1 + dir), PAWN));
 
-                for y in 0..8usize {
-                    for x in 0..8usize {
-                        if self.board[y][x].team == -team { enemy_indices.push((x,y)); }
-                    }
-                }
+        let knight: [(i8, i8); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
+        for k in knight.iter() { candidates.push(((t.0 + k.0, t.1 + k.1), KNIGHT)); }
 
-                for i in enemy_indices.iter() {
-                    let current_index: (i8, i8) = (i.0 as i8, i.1 as i8);
-                    let mut moves: Vec<(usize, usize, Flags)> = vec![];
-                    
-                    match self.board[i.1][i.0].id {
-                        1 => { moves.append(&mut self.gen_pawn_move(current_index, -team)); }
-                        2 => { moves.append(&mut self.gen_rook_move(current_index, -team)); }
-                        3 => { moves.append(&mut self.gen_knight_move(current_index, -team)); }
-                        4 => { moves.append(&mut self.gen_bishop_move(current_index, -team)); }
-                        5 => { moves.append(&mut self.gen_queen_move(current_index, -team)); }
-                        6 => { moves.append(&mut self.gen_king_move(current_index, -team)); }
-        
-                        _ => { }
-                    }
-        
-                    enemy_moves.insert(i.to_owned(), moves);
-                }
+        let king: [(i8, i8); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (1, 1), (0, 1), (-1, 1)];
+        for k in king.iter() { candidates.push(((t.0 + k.0, t.1 + k.1), KING)); }
 
-                for ek in enemy_moves.iter() {
-                    let ev = ek.1;
+        for (k, kinds) in RAYS.iter() {
+            let mut d: (i8, i8) = (t.0 + k.0, t.1 + k.1);
 
-                    for em in ev {
-                        if em.0 == ki.0 && em.1 == ki.1 && !bad_moves.contains(&(k.0.0, k.0.1, index)) {
-                            bad_moves.push((k.0.0, k.0.1, index));
-                            break;
-                        }
-                    }
-                }
-                
-                // Swap back
-                self.board[k.0.1][k.0.0] = p0;
-                self.board[m.1][m.0] = p1;
-            }
+            // The first piece on the ray blocks everything behind it.
+            while inside(d) && board[d.1 as usize][d.0 as usize].is_none() { d = (d.0 + k.0, d.1 + k.1); }
+            candidates.push((d, kinds));
         }
 
-        // Delete all bad moves.
-        for bm in bad_moves.iter() {
-            self.move_list.get_mut(&(bm.0, bm.1)).unwrap()[bm.2] = (usize::MAX, usize::MAX, Flags::None);
+        let mut found: Vec<(usize, usize)> = vec![];
+        for (d, kinds) in candidates {
+            if inside(d) &&
+               board[d.1 as usize][d.0 as usize].is_some_and(|p| p.color == team && kinds.contains(&p.kind)) {
+                found.push((d.0 as usize, d.1 as usize));
+                if first_only { break; }
+            }
         }
 
-        for k in self.move_list.iter_mut() {
-            k.1.retain(|&m| m.0 != usize::MAX && m.1 != usize::MAX);
-        }
+        return found;
+    }
+
+    /// Generate moves for the piece on a tile, ordered by destination index.
+    fn gen_piece_moves(&self, indices: (usize, usize), team: Color) -> Vec<Move> {
+        let current_index: (i8, i8) = (indices.0 as i8, indices.1 as i8);
 
-        self.move_list.retain(|&_, v| !v.is_empty());
+        let mut moves = match self.board[indices.1][indices.0].map(|p| p.kind) {
+            Some(PieceKind::Pawn) => { self.gen_pawn_move(current_index, team) }
+            Some(PieceKind::Rook) => { self.gen_rook_move(current_index, team) }
+            Some(PieceKind::Knight) => { self.gen_knight_move(current_index, team) }
+            Some(PieceKind::Bishop) => { self.gen_bishop_move(current_index, team) }
+            Some(PieceKind::Queen) => { self.gen_queen_move(current_index, team) }
+            Some(PieceKind::King) => { self.gen_king_move(current_index, team) }
+
+            None => { vec![] }
+        };
+
+        moves.sort_by_key(|m| m.to);
+        return moves;
     }
 
     /// Generate pawn moves.
-    fn gen_pawn_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
-        let kernel: [(i8, i8); 4] = [(0, 1 * team), (0, 2 * team), (-1, 1 * team), (1, 1 * team)];
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+    fn gen_pawn_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let from: (usize, usize) = (index.0 as usize, index.1 as usize);
+        let dir: i8 = if team == Color::White { -1 } else { 1 };
+        let kernel: [(i8, i8); 4] = [(0, dir), (0, 2 * dir), (-1, dir), (1, dir)];
+        let mut moves: Vec<Move> = vec![];
 
         // Forward move.
         if self.within_board((index.0 + kernel[0].0, index.1 + kernel[0].1)) &&
            self.empty_tile(((index.0 + kernel[0].0) as usize, (index.1 + kernel[0].1) as usize)) {
 
-            moves.push(((index.0 + kernel[0].0) as usize, (index.1 + kernel[0].1) as usize, Flags::None));
+            moves.push(Move::new(from, ((index.0 + kernel[0].0) as usize, (index.1 + kernel[0].1) as usize), MoveKind::Quiet));
         }
 
         // Double forward move.
         if self.within_board((index.0 + kernel[1].0, index.1 + kernel[1].1)) &&
-           !self.board[index.1 as usize][index.0 as usize].moved &&
+           index.1 == if team == Color::White { 6 } else { 1 } &&
+           self.empty_tile(((index.0 + kernel[0].0) as usize, (index.1 + kernel[0].1) as usize)) &&
            self.empty_tile(((index.0 + kernel[1].0) as usize, (index.1 + kernel[1].1) as usize)) {
 
-            moves.push(((index.0 + kernel[1].0) as usize, (index.1 + kernel[1].1) as usize, Flags::TwoSteps));
+            moves.push(Move::new(from, ((index.0 + kernel[1].0) as usize, (index.1 + kernel[1].1) as usize), MoveKind::DoubleStep));
         }
 
         // Diagonals
         if self.within_board((index.0 + kernel[2].0, index.1 + kernel[2].1)) &&
            self.enemy_tile(((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize), team) {
 
-            moves.push(((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize, Flags::Capture));
+            moves.push(Move::new(from, ((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize), MoveKind::Capture));
         }
 
         if self.within_board((index.0 + kernel[3].0, index.1 + kernel[3].1)) &&
            self.enemy_tile(((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize), team) {
 
-            moves.push(((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize, Flags::Capture));
+            moves.push(Move::new(from, ((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize), MoveKind::Capture));
         }
 
         // En passant
         if self.within_board((index.0 + kernel[2].0, index.1 + kernel[2].1)) &&
            self.empty_tile(((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize)) &&
-           self.enemy_tile(((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1 - team) as usize), team) &&
-           self.board[(index.1 + kernel[2].1 - team) as usize][(index.0 + kernel[2].0) as usize].moved_twice {
+           self.enemy_tile(((index.0 + kernel[2].0) as usize, index.1 as usize), team) &&
+           self.board[index.1 as usize][(index.0 + kernel[2].0) as usize].is_some_and(|p| p.moved_twice) {
 
-            moves.push(((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize, Flags::EnPassant));
+            moves.push(Move::new(from, ((index.0 + kernel[2].0) as usize, (index.1 + kernel[2].1) as usize), MoveKind::EnPassant));
         }
 
         if self.within_board((index.0 + kernel[3].0, index.1 + kernel[3].1)) &&
            self.empty_tile(((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize)) &&
-           self.enemy_tile(((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1 - team) as usize), team) &&
-           self.board[(index.1 + kernel[3].1 - team) as usize][(index.0 + kernel[3].0) as usize].moved_twice {
+           self.enemy_tile(((index.0 + kernel[3].0) as usize, index.1 as usize), team) &&
+           self.board[index.1 as usize][(index.0 + kernel[3].0) as usize].is_some_and(|p| p.moved_twice) {
 
-            moves.push(((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize, Flags::EnPassant));
+            moves.push(Move::new(from, ((index.0 + kernel[3].0) as usize, (index.1 + kernel[3].1) as usize), MoveKind::EnPassant));
         }
 
         return moves;
     }
 
     // Generate rook moves.
-    fn gen_rook_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
+    fn gen_rook_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let from: (usize, usize) = (index.0 as usize, index.1 as usize);
         let kernel: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+        let mut moves: Vec<Move> = vec![];
 
         for k in kernel.iter() {
             let mut d: (i8, i8) = (index.0 + k.0, index.1 + k.1);
 
             while self.within_board(d) {
                 if self.enemy_tile((d.0 as usize, d.1 as usize), team) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::Capture));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Capture));
                     break;
                 } else if self.empty_tile((d.0 as usize, d.1 as usize)) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::None));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Quiet));
                 } else {
                     break;
                 }
@@ -563,17 +2142,18 @@ impl ChessBoard {
     }
 
     // Generate knight moves.
-    fn gen_knight_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
+    fn gen_knight_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let from: (usize, usize) = (index.0 as usize, index.1 as usize);
         let kernel: [(i8, i8); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (-1, 2), (1, -2), (-1, -2)];
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+        let mut moves: Vec<Move> = vec![];
         
         for k in kernel.iter() {
             let d: (i8, i8) = (index.0 + k.0, index.1 + k.1);
             if self.within_board(d) {
                 if self.enemy_tile((d.0 as usize, d.1 as usize), team) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::Capture));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Capture));
                 } else if self.empty_tile((d.0 as usize, d.1 as usize)) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::None));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Quiet));
                 }
             }
         }
@@ -582,19 +2162,20 @@ impl ChessBoard {
     }
 
     // Generate bishop moves.
-    fn gen_bishop_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
+    fn gen_bishop_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let from: (usize, usize) = (index.0 as usize, index.1 as usize);
         let kernel: [(i8, i8); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+        let mut moves: Vec<Move> = vec![];
 
         for k in kernel.iter() {
             let mut d: (i8, i8) = (index.0 + k.0, index.1 + k.1);
 
             while self.within_board(d) {
                 if self.enemy_tile((d.0 as usize, d.1 as usize), team) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::Capture));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Capture));
                     break;
                 } else if self.empty_tile((d.0 as usize, d.1 as usize)) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::None));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Quiet));
                 } else {
                     break;
                 }
@@ -607,8 +2188,8 @@ impl ChessBoard {
     }
 
     // Generate queen moves.
-    fn gen_queen_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+    fn gen_queen_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let mut moves: Vec<Move> = vec![];
         moves.append(&mut self.gen_rook_move(index, team));
         moves.append(&mut self.gen_bishop_move(index, team));
 
@@ -616,63 +2197,365 @@ impl ChessBoard {
     }
 
     // Generate king moves.
-    fn gen_king_move(&self, index: (i8, i8), team: i8) -> Vec<(usize, usize, Flags)> {
+    fn gen_king_move(&self, index: (i8, i8), team: Color) -> Vec<Move> {
+        let from: (usize, usize) = (index.0 as usize, index.1 as usize);
         let kernel: [(i8, i8); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (1, 1), (0, 1), (-1, 1)];
-        let mut moves: Vec<(usize, usize, Flags)> = vec![];
+        let mut moves: Vec<Move> = vec![];
 
         for k in kernel.iter() {
             let d: (i8, i8) = (index.0 + k.0, index.1 + k.1);
 
             if self.within_board(d) {
                 if self.enemy_tile((d.0 as usize, d.1 as usize), team) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::Capture));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Capture));
                 } else if self.empty_tile((d.0 as usize, d.1 as usize)) {
-                    moves.push((d.0 as usize, d.1 as usize, Flags::None));
+                    moves.push(Move::new(from, (d.0 as usize, d.1 as usize), MoveKind::Quiet));
                 }
             }
         }
 
-        let r: usize = if team == -1 { 7 } else { 0 };
+        let r: usize = if team == Color::White { 7 } else { 0 };
         if r == 7 {
-            if self.wqcr && self.empty_tile((1, r)) && self.empty_tile((2, r)) && self.empty_tile((3, r)) { moves.push((2, r, Flags::Qastling)); } 
-            if self.wkcr && self.empty_tile((5, r)) && self.empty_tile((6, r)) { moves.push((6, r, Flags::Kastling)); }
+            if self.castling.white_queenside && self.empty_tile((1, r)) && self.empty_tile((2, r)) && self.empty_tile((3, r)) { moves.push(Move::new(from, (2, r), MoveKind::QueensideCastle)); } 
+            if self.castling.white_kingside && self.empty_tile((5, r)) && self.empty_tile((6, r)) { moves.push(Move::new(from, (6, r), MoveKind::KingsideCastle)); }
         } else {
-            if self.bqcr && self.empty_tile((1, r)) && self.empty_tile((2, r)) && self.empty_tile((3, r)) { moves.push((2, r, Flags::Qastling)); } 
-            if self.bkcr && self.empty_tile((5, r)) && self.empty_tile((6, r)) { moves.push((6, r, Flags::Kastling)); }
+            if self.castling.black_queenside && self.empty_tile((1, r)) && self.empty_tile((2, r)) && self.empty_tile((3, r)) { moves.push(Move::new(from, (2, r), MoveKind::QueensideCastle)); } 
+            if self.castling.black_kingside && self.empty_tile((5, r)) && self.empty_tile((6, r)) { moves.push(Move::new(from, (6, r), MoveKind::KingsideCastle)); }
         }
 
         return moves;
     }
 
+    /// Take a snapshot of the current position.
+    fn state(&self) -> State {
+        return State {
+            board: self.board,
+            status: self.status,
+            white_turn: self.white_turn,
+            castling: self.castling,
+            promoting: self.promoting,
+            promoting_index: self.promoting_index,
+            last_move: self.last_move,
+            captured: self.captured.clone(),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number
+        };
+    }
+
+    /// Restore a snapshot and regenerate the move list.
+    fn restore(&mut self, state: State) {
+        self.board = state.board;
+        self.status = state.status;
+        self.white_turn = state.white_turn;
+        self.castling = state.castling;
+        self.promoting = state.promoting;
+        self.promoting_index = state.promoting_index;
+        self.last_move = state.last_move;
+        self.captured = state.captured;
+        self.halfmove_clock = state.halfmove_clock;
+        self.fullmove_number = state.fullmove_number;
+        self.zobrist = self.compute_zobrist();
+        self.dirty = [u64::MAX; 2];
+        self.gen_moves();
+    }
+
+    /// Get the tiles a piece passes from one tile to another, both included.
+    fn ray(from: (usize, usize), to: (usize, usize), kind: PieceKind) -> Vec<(usize, usize)> {
+        // Only sliding pieces pass through tiles.
+        if !matches!(kind, PieceKind::Rook | PieceKind::Bishop | PieceKind::Queen) { return vec![from, to]; }
+
+        let d: (i8, i8) = ((to.0 as i8 - from.0 as i8).signum(), (to.1 as i8 - from.1 as i8).signum());
+        let mut tiles: Vec<(usize, usize)> = vec![from];
+        let mut t: (i8, i8) = (from.0 as i8, from.1 as i8);
+
+        while (t.0 as usize, t.1 as usize) != to {
+            t = (t.0 + d.0, t.1 + d.1);
+            tiles.push((t.0 as usize, t.1 as usize));
+        }
+
+        return tiles;
+    }
+
+    /// Get the name of a piece kind.
+    fn piece_name(kind: PieceKind) -> &'static str {
+        return match kind {
+            PieceKind::Pawn => { "Pawn" }
+            PieceKind::Rook => { "Rook" }
+            PieceKind::Knight => { "Knight" }
+            PieceKind::Bishop => { "Bishop" }
+            PieceKind::Queen => { "Queen" }
+            PieceKind::King => { "King" }
+        };
+    }
+
+    /// Get the Unicode chess glyph of a piece.
+    fn glyph(kind: PieceKind, color: Color) -> char {
+        return match (color, kind) {
+            (Color::White, PieceKind::King) => { '♔' }
+            (Color::White, PieceKind::Queen) => { '♕' }
+            (Color::White, PieceKind::Rook) => { '♖' }
+            (Color::White, PieceKind::Bishop) => { '♗' }
+            (Color::White, PieceKind::Knight) => { '♘' }
+            (Color::White, PieceKind::Pawn) => { '♙' }
+            (Color::Black, PieceKind::King) => { '♚' }
+            (Color::Black, PieceKind::Queen) => { '♛' }
+            (Color::Black, PieceKind::Rook) => { '♜' }
+            (Color::Black, PieceKind::Bishop) => { '♝' }
+            (Color::Black, PieceKind::Knight) => { '♞' }
+            (Color::Black, PieceKind::Pawn) => { '♟' }
+        };
+    }
+
+    /// Get the letter of a piece, upper case for white and lower case for black.
+    fn letter(kind: PieceKind, color: Color) -> char {
+        let c = match kind {
+            PieceKind::Pawn => { 'P' }
+            PieceKind::Rook => { 'R' }
+            PieceKind::Knight => { 'N' }
+            PieceKind::Bishop => { 'B' }
+            PieceKind::Queen => { 'Q' }
+            PieceKind::King => { 'K' }
+        };
+
+        return if color == Color::White { c } else { c.to_ascii_lowercase() };
+    }
+
+    /// Compute the Zobrist hash of the position from scratch.
+    fn compute_zobrist(&self) -> u64 {
+        let mut hash = Self::castling_key(self.castling) ^ Self::en_passant_key(self.en_passant_square());
+        if !self.white_turn { hash ^= ZOBRIST[768]; }
+
+        for y in 0..8usize {
+            for x in 0..8usize {
+                if let Some(p) = self.board[y][x] { hash ^= Self::piece_key(p, (x, y)); }
+            }
+        }
+
+        return hash;
+    }
+
+    /// Hash the pieces and the side to move only, e.g. to look positions up in `openings`.
+    pub(crate) fn placement_hash(&self) -> u64 { return Self::placement_key(&self.board, self.white_turn); }
+
+    /// Hash the pieces of a board and the side to move with the Zobrist keys.
+    fn placement_key(board: &[[Option<Piece>; 8]; 8], white_turn: bool) -> u64 {
+        let mut hash = if white_turn { 0 } else { ZOBRIST[768] };
+        for (y, row) in board.iter().enumerate() {
+            for (x, p) in row.iter().enumerate() {
+                if let Some(p) = p { hash ^= Self::piece_key(*p, (x, y)); }
+            }
+        }
+
+        return hash;
+    }
+
+    /// Get the Zobrist key of a piece on a tile.
+    fn piece_key(piece: Piece, indices: (usize, usize)) -> u64 {
+        return ZOBRIST[(piece.color as usize * 6 + piece.kind as usize) * 64 + indices.1 * 8 + indices.0];
+    }
+
+    /// Get the Zobrist key of a set of castling rights.
+    fn castling_key(castling: CastlingRights) -> u64 {
+        let rights = [castling.white_kingside, castling.white_queenside, castling.black_kingside, castling.black_queenside];
+        return rights.iter().enumerate().filter(|r| *r.1).fold(0, |key, (i, _)| key ^ ZOBRIST[769 + i]);
+    }
+
+    /// Get the Zobrist key of an en passant square.
+    fn en_passant_key(square: Option<Square>) -> u64 { return square.map_or(0, |s| ZOBRIST[773 + s.file().index()]); }
+
+    /// Get what identifies the position, see `ChessBoard` for what is included.
+    #[allow(clippy::type_complexity)]
+    fn position_key(&self) -> ([Option<(PieceKind, Color)>; 64], bool, CastlingRights, Option<Square>, Option<(usize, usize)>) {
+        let promoting = if self.promoting { Some(self.promoting_index) } else { None };
+        return (self.get_board(), self.white_turn, self.castling, self.en_passant_square(), promoting);
+    }
+
+    /// Press the clock for the player who just moved, or stop it if the game has ended.
+    fn press_clock(&mut self) {
+        let mover = self.turn().opposite();
+        if self.is_game_ended() { self.stop_clock(); return; }
+        if let Some(clock) = &mut self.clock { clock.press(mover); }
+    }
+
+    /// Stop both clocks, if the game is timed. Called on every path that ends the game.
+    fn stop_clock(&mut self) {
+        if let Some(clock) = &mut self.clock { clock.stop(); }
+    }
+
+    /// Set up the pawn that just moved two tiles past an en passant square, as if the move was played.
+    fn set_en_passant(&mut self, square: Square) -> Result<(), PositionError> {
+        let (x, y) = square.xy();
+        let (from, to, color, behind) = if self.white_turn { ((x, 1), (x, 3), Color::Black, 2) } else { ((x, 6), (x, 4), Color::White, 5) };
+
+        let pawn = self.board[to.1][to.0].is_some_and(|p| p.kind == PieceKind::Pawn && p.color == color);
+        if y != behind || !pawn || self.board[y][x].is_some() || self.board[from.1][from.0].is_some() { return Err(PositionError::InvalidEnPassant); }
+
+        if let Some(p) = &mut self.board[to.1][to.0] { p.moved_twice = true; }
+        self.last_move = Some(Move::new(from, to, MoveKind::DoubleStep));
+        self.zobrist = self.compute_zobrist();
+        self.dirty = [u64::MAX; 2];
+        self.update_status();
+
+        return Ok(());
+    }
+
+    /// Get the color of the side to move.
+    fn turn(&self) -> Color { return if self.white_turn { Color::White } else { Color::Black }; }
+
     /// Check if tile is empty.
-    fn empty_tile(&self, indices: (usize, usize)) -> bool { return self.board[indices.1][indices.0].id == 0; }
+    fn empty_tile(&self, indices: (usize, usize)) -> bool { return self.board[indices.1][indices.0].is_none(); }
 
     /// Check if tile is enemy tile.
-    fn enemy_tile(&self, indices: (usize, usize), team: i8) -> bool { return self.board[indices.1][indices.0].team == -team; }
+    fn enemy_tile(&self, indices: (usize, usize), team: Color) -> bool {
+        return self.board[indices.1][indices.0].is_some_and(|p| p.color != team);
+    }
 
     /// Check if indices are within board bounds.
     fn within_board(&self, indices: (i8, i8)) -> bool { return indices.0 < 8 && indices.0 > -1 && indices.1 < 8 && indices.1 > -1 }
 
-    /// Print the board to the terminal.
+    /// Print the board to the terminal with ANSI colors.
     pub fn print(&self) {
-        for y in 0..8usize {
-            for x in 0..8usize {
-                let col = if self.board[y][x].team == -1 { "32;49" } else { "31;49" };
-                print!("\x1b[{}m{}\x1b[0m ", col,
-                    match self.board[y][x].id {
-                        1 => { "P" }
-                        2 => { "R" }
-                        3 => { "k" }
-                        4 => { "B" }
-                        5 => { "Q" }
-                        6 => { "K" }
-                        _ => { " " }
-                    }
-                );
+        print!("{}\n\n", self.render(&RenderOptions::default()));
+    }
+
+    /**
+    Draw the board as text.                                                 <br/>
+    Parameters:                                                             <br/>
+    `options`: Labels, orientation, glyphs and colors to use                <br/>
+    Returns:                                                                <br/>
+    One line per rank, with the tiles separated by spaces.
+    */
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let mut out = String::new();
+        // Seen from black's side both the ranks and the files are reversed.
+        let order: Vec<usize> = if options.perspective == Color::White { (0..8).collect() } else { (0..8).rev().collect() };
+
+        for &y in order.iter() {
+            let mut tiles: Vec<String> = vec![];
+            if options.coordinates { tiles.push((8 - y).to_string()); }
+
+            for &x in order.iter() {
+                let piece = match self.board[y][x] {
+                    Some(p) => { p }
+                    None => { tiles.push(if options.unicode { "·" } else { "." }.to_string()); continue; }
+                };
+
+                let c = if options.unicode { Self::glyph(piece.kind, piece.color) } else { Self::letter(piece.kind, piece.color) };
+                if options.colors {
+                    let col = if piece.color == Color::White { "32;49" } else { "31;49" };
+                    tiles.push(format!("\x1b[{}m{}\x1b[0m", col, c));
+                } else {
+                    tiles.push(c.to_string());
+                }
+            }
+
+            out.push_str(&tiles.join(" "));
+            out.push('\n');
+        }
+
+        if options.coordinates {
+            let files: Vec<String> = order.iter().map(|&x| ((b'a' + x as u8) as char).to_string()).collect();
+            out.push_str(&format!("  {}\n", files.join(" ")));
+        }
+
+        return out;
+    }
+}
+
+impl Default for ChessBoard {
+    fn default() -> Self { return Self::new(); }
+}
+
+impl fmt::Display for ChessBoard {
+    /// Draw the board with Unicode chess glyphs, white at the bottom.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = RenderOptions { unicode: true, colors: false, ..RenderOptions::default() };
+        return write!(f, "{}", self.render(&options).trim_end());
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ChessBoard> for SavedBoard {
+    fn from(board: ChessBoard) -> SavedBoard {
+        return SavedBoard { position: board.state(), draw_offer: board.draw_offer, history: board.history, future: board.future };
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SavedBoard> for ChessBoard {
+    type Error = PositionError;
+
+    /// Load a saved board, rejecting positions the move generator can't handle.
+    fn try_from(saved: SavedBoard) -> Result<ChessBoard, PositionError> {
+        for state in std::iter::once(&saved.position).chain(saved.history.iter()).chain(saved.future.iter()) {
+            for color in [Color::White, Color::Black] {
+                let kings = state.board.iter().flatten().flatten().filter(|p| p.kind == PieceKind::King && p.color == color).count();
+                if kings != 1 { return Err(PositionError::KingCount(color)); }
             }
-            print!("\n");
         }
-        print!("\n\n");
+
+        let mut board = ChessBoard::new();
+        board.restore(saved.position);
+        board.validate_position()?;
+        board.draw_offer = saved.draw_offer;
+        board.history = saved.history;
+        board.future = saved.future;
+
+        return Ok(board);
+    }
+}
+
+impl TryFrom<([Option<(PieceKind, Color)>; 64], Color, CastlingRights)> for ChessBoard {
+    type Error = PositionError;
+
+    /**
+    Set up a board from tiles in the format of `get_board()`, the side to move and the castling   <br/>
+    rights, e.g. for board editors. The game starts from there with no history.                  <br/>
+    Returns:                                                                                      <br/>
+    The board, or why the position can't occur in a game.
+    */
+    fn try_from(snapshot: ([Option<(PieceKind, Color)>; 64], Color, CastlingRights)) -> Result<ChessBoard, PositionError> {
+        let (squares, turn, castling) = snapshot;
+        let mut board = ChessBoard::new();
+
+        for (i, tile) in squares.iter().enumerate() {
+            board.board[i / 8][i % 8] = tile.map(|(kind, color)| Piece::new(kind, color));
+        }
+        board.white_turn = turn == Color::White;
+        board.castling = castling;
+        board.zobrist = board.compute_zobrist();
+        board.dirty = [u64::MAX; 2];
+        board.update_status();
+        board.validate_position()?;
+
+        return Ok(board);
+    }
+}
+
+impl PartialEq for ChessBoard {
+    fn eq(&self, other: &Self) -> bool { return self.position_key() == other.position_key(); }
+}
+
+impl Eq for ChessBoard {}
+
+impl std::hash::Hash for ChessBoard {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) { self.position_key().hash(state); }
+}
+
+impl fmt::Debug for ChessBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pieces: Vec<String> = (0..64usize)
+            .filter_map(|i| self.get_board()[i].map(|(k, c)| format!("{:?} {:?} {}", c, k, Square::new(i).unwrap())))
+            .collect();
+
+        return f.debug_struct("ChessBoard")
+            .field("pieces", &pieces)
+            .field("turn", &self.turn())
+            .field("castling", &self.castling)
+            .field("en_passant", &self.en_passant_square())
+            .field("promoting", &self.promoting)
+            .field("status", &self.status)
+            .finish();
     }
 }
 
@@ -680,4 +2563,1039 @@ impl ChessBoard {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::PieceKind::*;
+    use super::Color::*;
+
+    #[test]
+    fn undo_redo_restores_position() {
+        let mut board = ChessBoard::new();
+        let start = board.get_board();
+
+        assert!(!board.undo());
+        assert!(board.move_by_algebraic("e2", "e4").is_ok());
+        let after = board.get_board();
+
+        assert!(board.undo());
+        assert_eq!(board.get_board(), start);
+        assert!(board.get_player());
+
+        assert!(board.redo());
+        assert_eq!(board.get_board(), after);
+        assert!(!board.get_player());
+        assert!(!board.redo());
+    }
+
+    fn play(board: &mut ChessBoard, moves: &[(&str, &str)]) {
+        for (f, t) in moves { assert!(board.move_by_algebraic(f, t).is_ok(), "{f}{t}"); }
+    }
+
+    #[test]
+    fn status_tracks_check_and_mate() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("f7", "f5"), ("d1", "h5")]);
+        assert_eq!(board.status(), GameStatus::Check);
+        assert!(board.is_in_check(Color::Black));
+        assert!(!board.is_in_check(Color::White));
+        assert!(!board.is_game_ended());
+
+        board.reset();
+        play(&mut board, &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        assert_eq!(board.status(), GameStatus::Checkmate(Color::Black));
+        assert!(board.is_game_ended());
+
+        assert!(board.undo());
+        assert_eq!(board.status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn stalemate_is_not_checkmate() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[
+            ("e2", "e3"), ("a7", "a5"), ("d1", "h5"), ("a8", "a6"), ("h5", "a5"), ("h7", "h5"),
+            ("h2", "h4"), ("a6", "h6"), ("a5", "c7"), ("f7", "f6"), ("c7", "d7"), ("e8", "f7"),
+            ("d7", "b7"), ("d8", "d3"), ("b7", "b8"), ("d3", "h7"), ("b8", "c8"), ("f7", "g6"),
+            ("c8", "e6")
+        ]);
+
+        assert_eq!(board.status(), GameStatus::Stalemate);
+        assert!(board.is_game_ended());
+    }
+
+    #[test]
+    fn explain_move_names_the_checking_piece() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("e7", "e5"), ("d2", "d4"), ("f8", "b4")]);
+
+        assert_eq!(board.explain_move(35, 19), "Pawn on d4 can't reach d6.");
+        assert_eq!(board.explain_move(50, 42), "Pawn on c2 to c3 is legal.");
+        assert_eq!(board.explain_move(48, 40), "Bishop on b4 would capture the king on e1 along b4, c3, d2, e1.");
+        assert_eq!(board.get_board()[33], Some((Bishop, Black)));
+    }
+
+    #[test]
+    fn checksum_follows_position() {
+        let mut a = ChessBoard::new();
+        let mut b = ChessBoard::new();
+        let start = a.state_checksum();
+        assert_eq!(start, b.state_checksum());
+
+        play(&mut a, &[("g1", "f3"), ("g8", "f6")]);
+        play(&mut b, &[("g1", "f3"), ("b8", "c6")]);
+        assert_ne!(a.state_checksum(), b.state_checksum());
+
+        assert!(a.undo() && a.undo());
+        assert_eq!(a.state_checksum(), start);
+
+        // Knights going out and back reach the same position by another move order.
+        play(&mut a, &[("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")]);
+        assert_eq!(a.state_checksum(), start);
+
+        // The en passant square and castling rights are part of the position.
+        let fen = |fen: &str| ChessBoard::from_fen(fen).unwrap().state_checksum();
+        play(&mut a, &[("e2", "e4"), ("d7", "d5"), ("e4", "e5"), ("f7", "f5")]);
+        assert_eq!(a.state_checksum(), fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"));
+        assert_ne!(a.state_checksum(), fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"));
+        play(&mut a, &[("e1", "e2"), ("e8", "f7"), ("e2", "e1"), ("f7", "e8")]);
+        assert_eq!(a.state_checksum(), fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w - - 4 5"));
+        assert_ne!(a.state_checksum(), fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 4 5"));
+    }
+
+    /// Set up a position from (tile, kind, color) triples, with no castling rights.
+    fn setup(pieces: &[(&str, PieceKind, Color)], white_turn: bool) -> ChessBoard {
+        let mut board = ChessBoard::new();
+        board.board = [[None; 8]; 8];
+        for (tile, kind, color) in pieces {
+            let (x, y) = tile.parse::<Square>().unwrap().xy();
+            board.board[y][x] = Some(Piece::new(*kind, *color));
+        }
+        board.white_turn = white_turn;
+        board.castling = CastlingRights { white_kingside: false, white_queenside: false, black_kingside: false, black_queenside: false };
+        board.zobrist = board.compute_zobrist();
+        board.dirty = [u64::MAX; 2];
+        board.update_status();
+        return board;
+    }
+
+    #[test]
+    fn insufficient_material_is_a_draw() {
+        let mut board = setup(&[("e1", King, White), ("d3", Bishop, White), ("e8", King, Black), ("e4", Knight, Black)], true);
+        assert_eq!(board.status(), GameStatus::Ongoing);
+        play(&mut board, &[("d3", "e4")]);
+        assert_eq!(board.status(), GameStatus::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(board.move_by_algebraic("e8", "d8"), Err(MoveError::GameOver));
+
+        let board = setup(&[("e1", King, White), ("c1", Bishop, White), ("e8", King, Black), ("f8", Bishop, Black)], true);
+        assert_eq!(board.status(), GameStatus::Draw(DrawReason::InsufficientMaterial));
+
+        let board = setup(&[("e1", King, White), ("c1", Bishop, White), ("e8", King, Black), ("c8", Bishop, Black)], true);
+        assert_eq!(board.status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut board = ChessBoard::new();
+        assert!(!board.accept_draw());
+
+        assert!(board.offer_draw(Color::White));
+        assert!(!board.offer_draw(Color::Black));
+        assert!(board.decline_draw());
+        assert_eq!(board.draw_offer(), None);
+
+        // The offer stands after the offering side moves, and lapses when the opponent moves.
+        assert!(board.offer_draw(Color::White));
+        play(&mut board, &[("e2", "e4")]);
+        assert_eq!(board.draw_offer(), Some(Color::White));
+        play(&mut board, &[("e7", "e5")]);
+        assert_eq!(board.draw_offer(), None);
+
+        assert!(board.offer_draw(Color::White));
+        assert!(board.accept_draw());
+        assert_eq!(board.status(), GameStatus::Draw(DrawReason::Agreement));
+        assert_eq!(board.move_by_algebraic("g1", "f3"), Err(MoveError::GameOver));
+        assert!(!board.offer_draw(Color::Black));
+    }
+
+    #[test]
+    fn move_errors() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.move_by_index(64, 0), Err(MoveError::InvalidSquare));
+        assert_eq!(board.move_by_algebraic("e4", "e5"), Err(MoveError::NoPieceOnSquare));
+        assert_eq!(board.move_by_algebraic("e7", "e5"), Err(MoveError::NotYourTurn));
+        assert_eq!(board.move_by_algebraic("e2", "e5"), Err(MoveError::IllegalDestination));
+        assert_eq!(board.move_by_algebraic("e2", "e2"), Err(MoveError::IllegalDestination));
+        assert_eq!(board.move_by_algebraic("e2", "e4"), Ok(MoveOutcome::Played(GameStatus::Ongoing)));
+    }
+
+    #[test]
+    fn incremental_move_list() {
+        let full = |b: &ChessBoard| {
+            let mut moves: Vec<(Square, Square)> = b.pseudo_legal_moves().into_iter().filter(|m| b.is_legal(*m)).map(|m| (m.from, m.to)).collect();
+            moves.sort();
+            return moves;
+        };
+        let cached = |b: &ChessBoard| {
+            let mut moves: Vec<(Square, Square)> = b.legal_moves().map(|m| (m.from, m.to)).collect();
+            moves.sort();
+            return moves;
+        };
+
+        let starts = [ChessBoard::new(), ChessBoard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq").unwrap(),
+                      ChessBoard::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq").unwrap()];
+
+        // Play a fixed walk through each game, with some moves taken back along the way.
+        for mut board in starts {
+            for ply in 0..60usize {
+                let moves: Vec<Move> = board.legal_moves().collect();
+                if moves.is_empty() { break; }
+
+                let m = moves[ply * 7 % moves.len()];
+                assert!(board.move_by_square(m.from, m.to).is_ok());
+                if board.can_promote() { board.promote(PromotionPiece::Knight).unwrap(); }
+                if ply % 5 == 4 { board.undo(); }
+                let first = board.legal_moves().next();
+                if let Some(first) = first.filter(|_| ply % 9 == 8) {
+                    let undo = board.make(first).unwrap();
+                    board.refresh();
+                    assert_eq!(cached(&board), full(&board));
+                    board.unmake(undo);
+                }
+
+                assert_eq!(cached(&board), full(&board), "ply {ply}");
+            }
+        }
+    }
+
+    #[test]
+    fn move_order() {
+        let order = |b: &ChessBoard| b.legal_moves().map(|m| (m.from.index(), m.to.index())).collect::<Vec<(usize, usize)>>();
+
+        let mut a = ChessBoard::new();
+        let mut b = ChessBoard::new();
+        play(&mut a, &[("g1", "f3"), ("g8", "f6"), ("e2", "e4"), ("e7", "e5")]);
+        play(&mut b, &[("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("g8", "f6")]);
+
+        assert!(order(&a).is_sorted());
+        assert_eq!(order(&a), order(&b));
+        assert_eq!(order(&a), a.pseudo_legal_moves().into_iter().filter(|m| a.is_legal(*m)).map(|m| (m.from.index(), m.to.index())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn legal_moves_on_demand() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5"), ("b1", "c3")]);
+
+        // Matches the full move list for every square.
+        for i in 0..64 {
+            let square = Square::new(i).unwrap();
+            let mut cached: Vec<Square> = board.legal_moves().filter(|m| m.from == square).map(|m| m.to).collect();
+            let mut lazy: Vec<Square> = board.legal_moves_from(square).into_iter().map(|m| m.to).collect();
+            cached.sort();
+            lazy.sort();
+            assert_eq!(cached, lazy);
+        }
+
+        // make() skips the full move list, but a single piece can still be asked.
+        let a5 = board.legal_moves_from("d5".parse().unwrap()).into_iter().find(|m| m.to == "a5".parse().unwrap()).unwrap();
+        let undo = board.make(a5).unwrap();
+        assert_eq!(board.legal_moves().count(), 0);
+        assert_eq!(board.legal_moves_from("c3".parse().unwrap()).len(), 6);
+        board.unmake(undo);
+    }
+
+    #[test]
+    fn legal_moves_from_square() {
+        let board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        let mut moves = board.legal_moves_from(sq("e2"));
+        moves.sort_by_key(|m| m.to);
+        assert_eq!(moves, vec![
+            Move { from: sq("e2"), to: sq("e4"), kind: MoveKind::DoubleStep, promotion: None },
+            Move { from: sq("e2"), to: sq("e3"), kind: MoveKind::Quiet, promotion: None }
+        ]);
+
+        assert_eq!(board.legal_moves_from(sq("g1")).len(), 2);
+        assert!(board.legal_moves_from(sq("e1")).is_empty());
+        assert!(board.legal_moves_from(sq("e7")).is_empty());
+        assert!(board.legal_moves_from(sq("e4")).is_empty());
+    }
+
+    #[test]
+    fn legal_targets_mask() {
+        let board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        assert_eq!(board.legal_targets_mask(sq("g1")), 1 << sq("f3").index() | 1 << sq("h3").index());
+        assert_eq!(board.legal_targets_mask(sq("e2")), 1 << sq("e3").index() | 1 << sq("e4").index());
+        assert_eq!(board.legal_targets_mask(sq("e1")), 0);
+        assert_eq!(board.legal_targets_mask(sq("e7")), 0);
+
+        // After make() the cached moves are stale, but the mask follows the new position.
+        let mut board = ChessBoard::new();
+        let e4 = board.legal_moves_from(sq("e2")).into_iter().find(|m| m.to == sq("e4")).unwrap();
+        board.make(e4).unwrap();
+        assert_eq!(board.legal_targets_mask(sq("e2")), 0);
+        assert_eq!(board.legal_targets_mask(sq("e7")), 1 << sq("e6").index() | 1 << sq("e5").index());
+        assert_eq!(board.legal_targets_mask(sq("d8")), 0);
+    }
+
+    #[test]
+    fn all_legal_moves() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.legal_moves().count(), 20);
+        assert!(board.legal_moves().all(|m| board.legal_moves_from(m.from).contains(&m)));
+
+        play(&mut board, &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        assert_eq!(board.legal_moves().count(), 0);
+    }
+
+    #[test]
+    fn square_attacks() {
+        let mut board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        assert!(board.is_square_attacked(sq("e3"), Color::White));
+        assert!(board.is_square_attacked(sq("f3"), Color::White));
+        assert!(!board.is_square_attacked(sq("e4"), Color::White));
+        assert!(board.is_square_attacked(sq("e6"), Color::Black));
+        assert!(!board.is_square_attacked(sq("e3"), Color::Black));
+
+        play(&mut board, &[("e2", "e4"), ("e7", "e5"), ("d1", "h5")]);
+        assert!(board.is_square_attacked(sq("f7"), Color::White));
+        assert!(board.is_square_attacked(sq("e5"), Color::White));
+        assert!(!board.is_square_attacked(sq("e8"), Color::White));
+        assert!(!board.is_square_attacked(sq("h5"), Color::Black));
+        assert!(board.is_square_attacked(sq("g5"), Color::Black));
+
+        play(&mut board, &[("b8", "c6"), ("f1", "c4")]);
+        let (mut white, black) = board.attackers_of(sq("f7"));
+        white.sort();
+        assert_eq!(white, vec![sq("h5"), sq("c4")]);
+        assert_eq!(black, vec![sq("e8")]);
+        assert_eq!(board.attackers_of(sq("a4")), (vec![], vec![]));
+    }
+
+    #[test]
+    fn attacked_squares() {
+        let mut board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        assert_ne!(board.attacked_squares(White) & 1 << sq("f3").index(), 0);
+        assert_eq!(board.attacked_squares(White) & 1 << sq("e4").index(), 0);
+
+        // The masks agree with is_square_attacked() square by square.
+        for moves in [&[][..], &[("e2", "e4"), ("e7", "e5"), ("d1", "h5"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6")][..]] {
+            play(&mut board, moves);
+            for color in [White, Black] {
+                for i in 0..64 {
+                    let s = Square::new(i).unwrap();
+                    assert_eq!(board.attacked_squares(color) & 1 << i != 0, board.is_square_attacked(s, color), "{s}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        let board = setup(&[
+            ("e1", King, White), ("e2", Knight, White), ("d2", Bishop, White), ("g3", Pawn, White), ("f2", Pawn, White),
+            ("h8", King, Black), ("e8", Rook, Black), ("a5", Bishop, Black), ("h4", Queen, Black)
+        ], true);
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        let mut pins = board.pinned_pieces(White);
+        pins.sort();
+        assert_eq!(pins, vec![(sq("d2"), sq("a5")), (sq("e2"), sq("e8"))]);
+        assert!(board.pinned_pieces(Black).is_empty());
+    }
+
+    #[test]
+    fn king_squares() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        let mut board = ChessBoard::new();
+        assert_eq!((board.king_square(White), board.king_square(Black)), (sq("e1"), sq("e8")));
+
+        play(&mut board, &[("e2", "e4"), ("e7", "e5"), ("e1", "e2")]);
+        assert_eq!(board.king_square(White), sq("e2"));
+        board.undo();
+        assert_eq!(board.king_square(White), sq("e1"));
+    }
+
+    #[test]
+    fn gives_check() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("f7", "f6")]);
+        let before = board.get_board();
+
+        assert!(board.gives_check(sq("d1"), sq("h5")));
+        assert!(!board.gives_check(sq("d1"), sq("g4")));
+        assert!(!board.gives_check(sq("d1"), sq("h6")));
+        assert_eq!(board.get_board(), before);
+
+        // Discovered check, promotion and castling.
+        let board = setup(&[
+            ("e1", King, White), ("f1", Rook, White), ("f4", Bishop, White), ("b7", Pawn, White), ("f8", King, Black)
+        ], true);
+        assert!(board.gives_check(sq("f4"), sq("c1")));
+        assert!(board.gives_check(sq("b7"), sq("b8")));
+        assert!(!board.gives_check(sq("e1"), sq("d1")));
+
+        let mut board = setup(&[("e1", King, White), ("h1", Rook, White), ("a2", Pawn, White), ("f8", King, Black), ("a7", Pawn, Black)], true);
+        board.castling.white_kingside = true;
+        board.update_status();
+        assert!(board.gives_check(sq("e1"), sq("g1")));
+    }
+
+    #[test]
+    fn checkers() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        let mut board = ChessBoard::new();
+        assert!(board.checkers().is_empty());
+
+        play(&mut board, &[("e2", "e4"), ("f7", "f6"), ("d1", "h5")]);
+        assert_eq!(board.checkers(), vec![sq("h5")]);
+
+        // Double check from a knight and a rook.
+        let board = setup(&[
+            ("e1", King, White), ("e8", King, Black), ("e6", Rook, White), ("d6", Knight, White)
+        ], false);
+        let mut checks = board.checkers();
+        checks.sort();
+        assert_eq!(checks, vec![sq("d6"), sq("e6")]);
+    }
+
+    #[test]
+    fn oriented_board() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4")]);
+        assert_eq!(board.get_board_oriented(White), board.get_board());
+
+        let black = board.get_board_oriented(Black);
+        assert_eq!(black[0], Some((Rook, White)));
+        assert_eq!(black[3], Some((King, White)));
+        assert_eq!(black[27], Some((Pawn, White)));
+        assert_eq!(black[60], Some((Queen, Black)));
+    }
+
+    #[test]
+    fn piece_at() {
+        let mut board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        assert_eq!(board.piece_at(sq("e1")), Some((King, White)));
+        assert_eq!(board.piece_at(sq("b8")), Some((Knight, Black)));
+        assert_eq!(board.piece_at(sq("e4")), None);
+
+        play(&mut board, &[("e2", "e4")]);
+        assert_eq!(board.piece_at(sq("e4")), Some((Pawn, White)));
+        assert_eq!(board.piece_at(sq("e2")), None);
+    }
+
+    #[test]
+    fn last_move() {
+        let mut board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        assert_eq!(board.last_move(), None);
+
+        play(&mut board, &[("e2", "e4"), ("a7", "a6"), ("e4", "e5"), ("d7", "d5"), ("e5", "d6")]);
+        let m = board.last_move().unwrap();
+        assert_eq!((m.from, m.to, m.kind), (sq("e5"), sq("d6"), MoveKind::EnPassant));
+        assert_eq!(m.captured_square(), Some(sq("d5")));
+        assert_eq!(m.rook_squares(), None);
+
+        board.undo();
+        assert_eq!(board.last_move().map(|m| m.to), Some(sq("d5")));
+        board.redo();
+        assert_eq!(board.last_move().map(|m| m.to), Some(sq("d6")));
+
+        let mut board = setup(&[("e1", King, White), ("a1", Rook, White), ("e8", King, Black), ("g2", Pawn, Black)], false);
+        board.castling.white_queenside = true;
+        board.update_status();
+        play(&mut board, &[("g2", "g1")]);
+        assert_eq!(board.last_move().unwrap().promotion, None);
+        board.promote(PromotionPiece::Knight).unwrap();
+        assert_eq!(board.last_move().unwrap().promotion, Some(Knight));
+
+        play(&mut board, &[("e1", "c1")]);
+        assert_eq!(board.last_move().unwrap().rook_squares(), Some((sq("a1"), sq("d1"))));
+    }
+
+    #[test]
+    fn captured_pieces() {
+        let mut board = ChessBoard::new();
+
+        play(&mut board, &[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5"), ("b1", "c3"), ("d5", "a2")]);
+        assert_eq!(board.captured_by(White), &[Pawn]);
+        assert_eq!(board.captured_by(Black), &[Pawn, Pawn]);
+
+        play(&mut board, &[("a1", "a2")]);
+        assert_eq!(board.captured_by(White), &[Pawn, Queen]);
+
+        board.undo();
+        assert_eq!(board.captured_by(White), &[Pawn]);
+        board.reset();
+        assert!(board.captured_by(Black).is_empty());
+    }
+
+    #[test]
+    fn en_passant_square() {
+        let mut board = ChessBoard::new();
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        assert_eq!(board.en_passant_square(), None);
+
+        play(&mut board, &[("e2", "e4")]);
+        assert_eq!(board.en_passant_square(), Some(sq("e3")));
+        play(&mut board, &[("a7", "a6"), ("e4", "e5"), ("d7", "d5")]);
+        assert_eq!(board.en_passant_square(), Some(sq("d6")));
+        assert!(board.legal_moves_from(sq("e5")).iter().any(|m| m.kind == MoveKind::EnPassant));
+
+        // The chance is gone after one move.
+        play(&mut board, &[("g1", "f3"), ("a6", "a5")]);
+        assert_eq!(board.en_passant_square(), None);
+        assert!(!board.legal_moves_from(sq("e5")).iter().any(|m| m.kind == MoveKind::EnPassant));
+    }
+
+    #[test]
+    fn castling_rights() {
+        let mut board = ChessBoard::new();
+        let all = CastlingRights { white_kingside: true, white_queenside: true, black_kingside: true, black_queenside: true };
+        assert_eq!(board.castling_rights(), all);
+
+        play(&mut board, &[("b2", "b3"), ("a7", "a6"), ("c1", "b2"), ("a8", "a7")]);
+        assert_eq!(board.castling_rights(), CastlingRights { black_queenside: false, ..all });
+
+        // Taking the rook on h8 also takes the right to castle with it.
+        play(&mut board, &[("g2", "g3"), ("g7", "g6"), ("b2", "h8")]);
+        assert_eq!(board.castling_rights(), CastlingRights { black_queenside: false, black_kingside: false, ..all });
+
+        board.undo();
+        play(&mut board, &[("e2", "e3"), ("e7", "e6"), ("e1", "e2")]);
+        assert_eq!(board.castling_rights(), CastlingRights { black_queenside: false, white_kingside: false, white_queenside: false, ..all });
+    }
+
+    #[test]
+    fn move_counters() {
+        let mut board = ChessBoard::new();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (0, 1));
+
+        play(&mut board, &[("g1", "f3"), ("g8", "f6"), ("b1", "c3")]);
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (3, 2));
+
+        play(&mut board, &[("e7", "e5")]);
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (0, 3));
+        play(&mut board, &[("f3", "g1"), ("f6", "e4")]);
+        assert_eq!(board.halfmove_clock(), 2);
+        play(&mut board, &[("c3", "e4")]);
+        assert_eq!(board.halfmove_clock(), 0);
+
+        board.undo();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (2, 4));
+    }
+
+    #[test]
+    fn validate_position() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.validate_position(), Ok(()));
+        play(&mut board, &[("e2", "e4"), ("f7", "f6"), ("d1", "h5")]);
+        assert_eq!(board.validate_position(), Ok(()));
+
+        let board = setup(&[("e1", King, White), ("a8", Rook, Black)], true);
+        assert_eq!(board.validate_position(), Err(PositionError::KingCount(Black)));
+        let board = setup(&[("e1", King, White), ("e8", King, Black), ("c1", Pawn, Black)], true);
+        assert_eq!(board.validate_position(), Err(PositionError::PawnOnBackRank("c1".parse().unwrap())));
+        let board = setup(&[("e1", King, White), ("e8", King, Black), ("e4", Rook, White)], true);
+        assert_eq!(board.validate_position(), Err(PositionError::OpponentInCheck));
+
+        let mut board = setup(&[("e1", King, White), ("e8", King, Black), ("a1", Rook, White)], true);
+        board.castling.white_queenside = true;
+        assert_eq!(board.validate_position(), Ok(()));
+        board.castling.white_kingside = true;
+        assert_eq!(board.validate_position(), Err(PositionError::InvalidCastlingRights));
+
+        let mut board = setup(&[("e1", King, White), ("b7", Pawn, White), ("h8", King, Black)], true);
+        play(&mut board, &[("b7", "b8")]);
+        assert_eq!(board.validate_position(), Ok(()));
+    }
+
+    #[test]
+    fn board_equality() {
+        use std::collections::HashSet;
+
+        let mut board = ChessBoard::new();
+        let start = board.clone();
+        play(&mut board, &[("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")]);
+        assert_eq!(board, start);
+
+        let mut seen: HashSet<ChessBoard> = HashSet::new();
+        seen.insert(start.clone());
+        assert!(seen.contains(&board));
+
+        // Clones are independent.
+        let copy = board.clone();
+        play(&mut board, &[("e2", "e4")]);
+        assert_ne!(board, copy);
+        assert!(!seen.contains(&board));
+        assert_eq!(copy, start);
+
+        // The en passant square is part of the position.
+        let mut a = ChessBoard::new();
+        play(&mut a, &[("e2", "e4"), ("g8", "f6"), ("g1", "f3"), ("f6", "g8"), ("f3", "g1")]);
+        let mut b = ChessBoard::new();
+        play(&mut b, &[("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8"), ("e2", "e4")]);
+        assert_eq!(a.get_board(), b.get_board());
+        assert_ne!(a, b);
+        assert!(format!("{:?}", a).contains("White King e1"));
+    }
+
+    #[test]
+    fn display() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4")]);
+
+        let text = board.to_string();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[0], "♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜");
+        assert_eq!(rows[4], "· · · · ♙ · · ·");
+        assert_eq!(rows[7], "♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖");
+    }
+
+    #[test]
+    fn render() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4")]);
+
+        let plain = RenderOptions { colors: false, ..RenderOptions::default() };
+        let rows: Vec<String> = board.render(&plain).lines().map(String::from).collect();
+        assert_eq!(rows, ["r n b q k b n r", "p p p p p p p p", ". . . . . . . .", ". . . . . . . .",
+                          ". . . . P . . .", ". . . . . . . .", "P P P P . P P P", "R N B Q K B N R"]);
+
+        let flipped = RenderOptions { coordinates: true, perspective: Black, ..plain };
+        let text = board.render(&flipped);
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 9);
+        assert_eq!(rows[0], "1 R N B K Q B N R");
+        assert_eq!(rows[4], "5 . . . . . . . .");
+        assert_eq!(rows[3], "4 . . . P . . . .");
+        assert_eq!(rows[8], "  h g f e d c b a");
+
+        let colored = board.render(&RenderOptions { unicode: true, ..RenderOptions::default() });
+        assert!(colored.starts_with("\x1b[31;49m♜\x1b[0m"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("g8", "f6")]);
+
+        let json = serde_json::to_string(&board).unwrap();
+        let mut loaded: ChessBoard = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, board);
+        assert_eq!(loaded.legal_moves().count(), board.legal_moves().count());
+        assert_eq!(loaded.captured_by(White), &[Pawn]);
+        assert!(loaded.undo());
+        assert_eq!(loaded.last_move().map(|m| m.to), Some("d5".parse().unwrap()));
+
+        assert_eq!(serde_json::to_string(&"h1".parse::<Square>().unwrap()).unwrap(), "63");
+        assert!(serde_json::from_str::<Square>("64").is_err());
+
+        // Positions without both kings are rejected instead of breaking move generation.
+        let no_king = json.replacen("\"King\"", "\"Queen\"", 1);
+        assert!(serde_json::from_str::<ChessBoard>(&no_king).is_err());
+    }
+
+    #[test]
+    fn json() {
+        let mut board = ChessBoard::new();
+        let json: serde_json::Value = serde_json::from_str(&board.to_json()).unwrap();
+        assert_eq!(json["board"].as_array().unwrap().len(), 64);
+        assert_eq!(json["board"][4], serde_json::json!({"kind": "king", "color": "black"}));
+        assert_eq!(json["board"][36], serde_json::Value::Null);
+        assert_eq!(json["turn"], "white");
+        assert_eq!(json["status"], serde_json::json!({"state": "ongoing"}));
+        assert_eq!(json["legal_moves"]["g1"], serde_json::json!(["f3", "h3"]));
+        assert_eq!(json["legal_moves"].as_object().unwrap().len(), 10);
+        assert_eq!(json["history"], serde_json::json!([]));
+
+        play(&mut board, &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        let json: serde_json::Value = serde_json::from_str(&board.to_json()).unwrap();
+        assert_eq!(json["status"], serde_json::json!({"state": "checkmate", "winner": "black"}));
+        assert_eq!(json["history"], serde_json::json!(["f2f3", "e7e5", "g2g4", "d8h4"]));
+        assert_eq!(json["legal_moves"], serde_json::json!({}));
+
+        let mut board = setup(&[("e1", King, White), ("b7", Pawn, White), ("h8", King, Black)], true);
+        board.move_with_promotion("b7".parse().unwrap(), "b8".parse().unwrap(), PromotionPiece::Knight).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&board.to_json()).unwrap();
+        assert_eq!(json["history"], serde_json::json!(["b7b8n"]));
+        assert_eq!(json["status"], serde_json::json!({"state": "draw", "reason": "insufficient_material"}));
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        let mut board = ChessBoard::new();
+        let start = board.zobrist_hash();
+
+        // Castling, en passant, captures and promotion all keep the hash in step.
+        let moves = [("e2", "e4"), ("g8", "f6"), ("e4", "e5"), ("d7", "d5"), ("e5", "d6"), ("g7", "g6"), ("d6", "c7"),
+                     ("f8", "g7"), ("g1", "f3"), ("e8", "g8"), ("f1", "e2"), ("a7", "a6"), ("e1", "g1"), ("b7", "b6")];
+        for (f, t) in moves {
+            play(&mut board, &[(f, t)]);
+            assert_eq!(board.zobrist_hash(), board.compute_zobrist(), "{f}{t}");
+        }
+        play(&mut board, &[("c7", "d8")]);
+        board.promote(PromotionPiece::Queen).unwrap();
+        assert_eq!(board.zobrist_hash(), board.compute_zobrist());
+
+        board.reset();
+        play(&mut board, &[("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")]);
+        assert_eq!(board.zobrist_hash(), start);
+        play(&mut board, &[("e2", "e4")]);
+        assert_ne!(board.zobrist_hash(), start);
+        board.undo();
+        assert_eq!(board.zobrist_hash(), start);
+    }
+
+    #[test]
+    fn from_fen() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        let board = ChessBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let mut played = ChessBoard::new();
+        play(&mut played, &[("e2", "e4")]);
+        assert_eq!(board, played);
+        assert_eq!((board.en_passant_square(), board.zobrist_hash()), (Some(sq("e3")), played.zobrist_hash()));
+
+        // En passant can be taken, and the counters are kept.
+        let mut board = ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 42").unwrap();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (7, 42));
+        assert!(board.move_by_algebraic("e5", "d6").is_ok());
+        assert_eq!(board.piece_at(sq("d5")), None);
+
+        // Left out fields have defaults.
+        let board = ChessBoard::from_fen("4k3/8/8/8/8/8/8/4K3").unwrap();
+        assert_eq!((board.get_player(), board.en_passant_square(), board.halfmove_clock(), board.fullmove_number()), (true, None, 0, 1));
+
+        assert_eq!(ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - e6 0 2"), Err(PositionError::InvalidEnPassant));
+        assert_eq!(ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 b - d6 0 2"), Err(PositionError::InvalidEnPassant));
+        for fen in ["", "4k3/8/8/8/8/8/4K3", "4k3/8/8/8/8/8/8/4K3 x", "4k3/8/8/8/8/8/8/4K3 w KX", "4k3/8/8/8/8/8/8/4K3 w - - a 1", "4k3/8/8/8/8/8/8/4K3 w - - 0 0"] {
+            assert_eq!(ChessBoard::from_fen(fen), Err(PositionError::InvalidFen), "{fen}");
+        }
+        assert_eq!(ChessBoard::from_fen("4k3/8/8/8/8/8/8/4K3 w K"), Err(PositionError::InvalidCastlingRights));
+    }
+
+    #[test]
+    fn to_fen() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        play(&mut board, &[("e2", "e4"), ("g8", "f6"), ("g1", "f3"), ("h8", "g8")]);
+        assert_eq!(board.to_fen(), "rnbqkbr1/pppppppp/5n2/8/4P3/5N2/PPPP1PPP/RNBQKB1R w KQq - 3 3");
+
+        // The en passant square and both counters come back from an import.
+        for fen in ["4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 42", "r3k2r/8/8/8/4p3/8/3P4/R3K2R w Kq - 17 60", "4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 9"] {
+            assert_eq!(ChessBoard::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn clock() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.time_remaining(Color::White), None);
+
+        let minute = Duration::from_secs(60);
+        board.set_clock(Some(clock::Clock::new(minute, clock::Bonus::Fischer(Duration::from_secs(2)))));
+        play(&mut board, &[("f2", "f3")]);
+        assert_eq!((board.clock().unwrap().running(), board.time_remaining(Color::White)), (Some(Color::Black), Some(minute)));
+
+        // Black's move gets the increment, and mate stops the clocks.
+        play(&mut board, &[("e7", "e5"), ("g2", "g4")]);
+        assert!(board.time_remaining(Color::Black).unwrap() > minute + Duration::from_secs(1));
+        play(&mut board, &[("d8", "h4")]);
+        assert_eq!(board.clock().unwrap().running(), None);
+
+        // Replaying the mate stops a clock started again after taking it back.
+        board.undo();
+        let mut running = clock::Clock::new(minute, clock::Bonus::None);
+        running.start(Color::Black);
+        board.set_clock(Some(running));
+        board.redo();
+        assert_eq!(board.clock().unwrap().running(), None);
+
+        // So does a draw by agreement.
+        board.reset();
+        assert!(board.clock().is_none());
+        board.set_clock(Some(clock::Clock::new(minute, clock::Bonus::None)));
+        play(&mut board, &[("e2", "e4")]);
+        assert!(board.offer_draw(Color::White) && board.accept_draw());
+        assert_eq!(board.clock().unwrap().running(), None);
+        let left = board.time_remaining(Color::Black);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(board.time_remaining(Color::Black), left);
+        assert!(!board.check_flag());
+    }
+
+    #[test]
+    fn time_forfeit() {
+        let flagged = |color: Color| {
+            let mut clock = clock::Clock::new(Duration::ZERO, clock::Bonus::None);
+            clock.start(color);
+            return Some(clock);
+        };
+
+        let mut board = ChessBoard::new();
+        assert!(!board.check_flag());
+        board.set_clock(flagged(Color::White));
+        assert_eq!(board.move_by_algebraic("e2", "e4"), Err(MoveError::GameOver));
+        assert_eq!(board.status(), GameStatus::Timeout(Color::Black));
+        assert!(!board.check_flag());
+
+        // A lone bishop can't mate, so running out of time against it is a draw.
+        let mut board = setup(&[("e1", PieceKind::King, Color::White), ("d1", PieceKind::Queen, Color::White),
+                                ("e8", PieceKind::King, Color::Black), ("c8", PieceKind::Bishop, Color::Black)], true);
+        assert!(board.has_mating_material(Color::White) && !board.has_mating_material(Color::Black));
+        board.set_clock(flagged(Color::White));
+        assert!(board.check_flag());
+        assert_eq!(board.status(), GameStatus::Draw(DrawReason::Timeout));
+        assert_eq!(board.clock().unwrap().running(), None);
+
+        let board = setup(&[("e1", PieceKind::King, Color::White), ("b1", PieceKind::Knight, Color::White),
+                            ("g1", PieceKind::Knight, Color::White), ("e8", PieceKind::King, Color::Black), ("a7", PieceKind::Pawn, Color::Black)], true);
+        assert!(board.has_mating_material(Color::White) && board.has_mating_material(Color::Black));
+    }
+
+    #[test]
+    fn current_opening() {
+        let mut board = ChessBoard::new();
+        assert_eq!(board.current_opening(), None);
+
+        play(&mut board, &[("e2", "e4"), ("c7", "c5"), ("g1", "f3"), ("d7", "d6"), ("d2", "d4"), ("c5", "d4"),
+                           ("f3", "d4"), ("g8", "f6"), ("b1", "c3"), ("a7", "a6")]);
+        assert_eq!(board.current_opening().unwrap().to_string(), "B90 Sicilian Defence: Najdorf Variation");
+
+        // Out of the book, the game stays in the last opening it passed through.
+        play(&mut board, &[("c1", "e3"), ("e7", "e5")]);
+        assert_eq!(board.current_opening().map(|o| o.eco), Some("B90"));
+        board.undo();
+        board.undo();
+        board.undo();
+        assert_eq!(board.current_opening().map(|o| o.eco), Some("B56"));
+
+        // The Nimzo-Indian reached from the English.
+        board.reset();
+        play(&mut board, &[("c2", "c4"), ("e7", "e6"), ("b1", "c3"), ("g8", "f6"), ("d2", "d4"), ("f8", "b4")]);
+        assert_eq!(board.current_opening().map(|o| o.name), Some("Nimzo-Indian Defence"));
+    }
+
+    #[test]
+    fn make_and_unmake() {
+        fn snapshot(b: &ChessBoard) -> impl PartialEq + fmt::Debug {
+            let mut moves: Vec<(Square, Square)> = b.legal_moves().map(|m| (m.from, m.to)).collect();
+            moves.sort();
+            return (b.get_board(), b.zobrist_hash(), b.castling_rights(), b.en_passant_square(), b.last_move(), b.status(),
+                    b.captured_by(Color::White).to_vec(), b.captured_by(Color::Black).to_vec(), b.halfmove_clock(), b.fullmove_number(), moves);
+        }
+
+        let mut en_passant = ChessBoard::new();
+        play(&mut en_passant, &[("e2", "e4"), ("g8", "f6"), ("e4", "e5"), ("d7", "d5")]);
+        let mut castling = ChessBoard::new();
+        play(&mut castling, &[("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6")]);
+        let promotion = setup(&[("e1", PieceKind::King, Color::White), ("b7", PieceKind::Pawn, Color::White),
+                                ("e8", PieceKind::King, Color::Black), ("a8", PieceKind::Rook, Color::Black)], true);
+
+        for mut board in [en_passant, castling, promotion] {
+            let before = snapshot(&board);
+
+            for m in board.legal_moves().collect::<Vec<Move>>() {
+                let mut expected = board.clone();
+                assert!(expected.move_by_square(m.from, m.to).is_ok());
+                if expected.can_promote() { expected.promote(PromotionPiece::Queen).unwrap(); }
+
+                let undo = board.make(m).unwrap();
+                assert_eq!(board.zobrist_hash(), board.compute_zobrist());
+                board.refresh();
+                assert_eq!(snapshot(&board), snapshot(&expected), "{}{}", m.from, m.to);
+
+                board.unmake(undo);
+                assert_eq!(snapshot(&board), before);
+                board.refresh();
+                assert_eq!(snapshot(&board), before);
+            }
+        }
+    }
+
+    #[test]
+    fn board_from_snapshot() {
+        let none = CastlingRights { white_kingside: false, white_queenside: false, black_kingside: false, black_queenside: false };
+        let mut game = ChessBoard::new();
+        play(&mut game, &[("e2", "e4"), ("e7", "e5"), ("g1", "f3")]);
+
+        let board = ChessBoard::try_from((game.get_board(), Color::Black, game.castling_rights())).unwrap();
+        assert_eq!(board, game);
+        assert_eq!(board.legal_moves().count(), game.legal_moves().count());
+        assert_eq!(board.zobrist_hash(), game.zobrist_hash());
+        assert!(board.last_move().is_none());
+
+        let mut squares = game.get_board();
+        squares[0] = Some((PieceKind::King, Color::White));
+        assert_eq!(ChessBoard::try_from((squares, Color::Black, none)), Err(PositionError::KingCount(Color::White)));
+        assert!(ChessBoard::try_from((game.get_board(), Color::Black, CastlingRights { white_kingside: true, ..none })).is_ok());
+
+        squares = [None; 64];
+        squares[4] = Some((PieceKind::King, Color::Black));
+        squares[60] = Some((PieceKind::King, Color::White));
+        squares[12] = Some((PieceKind::Queen, Color::White));
+        assert_eq!(ChessBoard::try_from((squares, Color::White, none)), Err(PositionError::OpponentInCheck));
+        assert_eq!(ChessBoard::try_from((squares, Color::Black, game.castling_rights())), Err(PositionError::InvalidCastlingRights));
+        assert_eq!(ChessBoard::try_from((squares, Color::Black, none)).map(|b| b.status()), Ok(GameStatus::Check));
+    }
+
+    #[test]
+    fn make_keeps_the_board_playable() {
+        let mut board = setup(&[("e1", PieceKind::King, Color::White), ("e2", PieceKind::Rook, Color::White),
+                                ("e8", PieceKind::King, Color::Black), ("d2", PieceKind::Pawn, Color::Black)], true);
+        let m = |f: &str, t: &str, kind: MoveKind| Move { from: f.parse().unwrap(), to: t.parse().unwrap(), kind, promotion: None };
+
+        assert!(matches!(board.make(m("e2", "e8", MoveKind::Capture)), Err(MoveError::IllegalDestination)));
+        assert!(matches!(board.make(m("e2", "e1", MoveKind::Quiet)), Err(MoveError::IllegalDestination)));
+        assert!(matches!(board.make(m("d2", "d1", MoveKind::Quiet)), Err(MoveError::NotYourTurn)));
+        assert!(matches!(board.make(m("a1", "a2", MoveKind::Quiet)), Err(MoveError::NoPieceOnSquare)));
+
+        // Not a legal rook move, but the board stays consistent.
+        let undo = board.make(m("e2", "d2", MoveKind::Capture)).unwrap();
+        board.refresh();
+        assert_eq!(board.status(), GameStatus::Ongoing);
+        board.unmake(undo);
+        assert_eq!(board.piece_at("d2".parse().unwrap()), Some((PieceKind::Pawn, Color::Black)));
+    }
+
+    #[test]
+    fn pseudo_legal_moves() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        let is_move = |m: &Move, f: &str, t: &str| m.from == sq(f) && m.to == sq(t);
+
+        let mut board = ChessBoard::new();
+        assert_eq!(board.pseudo_legal_moves().len(), 20);
+        assert!(board.pseudo_legal_moves().iter().all(|m| board.is_legal(*m)));
+        assert!(!board.is_legal(Move { from: sq("e2"), to: sq("e5"), kind: MoveKind::Quiet, promotion: None }));
+
+        // Every legal move is a pseudo-legal move that passes is_legal(), also after make() and unmake().
+        play(&mut board, &[("e2", "e4"), ("d7", "d5"), ("e4", "e5"), ("f7", "f5"), ("d1", "h5")]);
+        let g6 = board.legal_moves().find(|m| is_move(m, "g7", "g6")).unwrap();
+        let undo = board.make(g6).unwrap();
+        assert!(board.pseudo_legal_moves().iter().any(|m| is_move(m, "h5", "g6")));
+        board.unmake(undo);
+        let mut legal: Vec<(Square, Square)> = board.legal_moves().map(|m| (m.from, m.to)).collect();
+        let mut checked: Vec<(Square, Square)> = board.pseudo_legal_moves().into_iter().filter(|m| board.is_legal(*m)).map(|m| (m.from, m.to)).collect();
+        legal.sort();
+        checked.sort();
+        assert_eq!(legal, checked);
+
+        // A pinned piece has pseudo-legal moves, but none are legal.
+        let board = setup(&[("e1", PieceKind::King, Color::White), ("e2", PieceKind::Bishop, Color::White),
+                            ("e8", PieceKind::Rook, Color::Black), ("h8", PieceKind::King, Color::Black)], true);
+        assert!(board.pseudo_legal_moves().iter().any(|m| m.from == sq("e2")));
+        assert!(board.pseudo_legal_moves().iter().filter(|m| m.from == sq("e2")).all(|m| !board.is_legal(*m)));
+
+        // Taking en passant would leave the king open to the rook along the fifth rank.
+        let mut board = setup(&[("a5", PieceKind::King, Color::White), ("b5", PieceKind::Pawn, Color::White),
+                                ("c7", PieceKind::Pawn, Color::Black), ("h5", PieceKind::Rook, Color::Black), ("h8", PieceKind::King, Color::Black)], false);
+        play(&mut board, &[("c7", "c5")]);
+        let en_passant = board.pseudo_legal_moves().into_iter().find(|m| m.kind == MoveKind::EnPassant).unwrap();
+        assert!(!board.is_legal(en_passant));
+    }
+
+    #[test]
+    fn mobility() {
+        let mut board = ChessBoard::new();
+        assert_eq!((board.mobility(Color::White), board.mobility(Color::Black)), (20, 20));
+
+        // Counted for both players after make(), which doesn't generate moves.
+        let e4 = board.legal_moves().find(|m| m.to.to_string() == "e4").unwrap();
+        board.make(e4).unwrap();
+        assert_eq!((board.mobility(Color::White), board.mobility(Color::Black)), (30, 20));
+    }
+
+    #[test]
+    fn perft() {
+        let perft = |board: &ChessBoard, counts: &[u64]| {
+            for (depth, count) in counts.iter().enumerate() { assert_eq!(board.perft(depth as u32 + 1), *count, "depth {}", depth + 1); }
+        };
+
+        perft(&ChessBoard::new(), &[20, 400, 8902]);
+        // Castling through check and with the rook attacked.
+        perft(&ChessBoard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq").unwrap(), &[48, 2039, 97862]);
+        // En passant discovering a check along the rank.
+        perft(&ChessBoard::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w -").unwrap(), &[14, 191, 2812, 43238]);
+        // Promotions, including by capture.
+        perft(&ChessBoard::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq").unwrap(), &[6, 264, 9467]);
+        perft(&ChessBoard::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ").unwrap(), &[44, 1486, 62379]);
+
+        // legal_moves() follows the same rules, the bishop guards f1.
+        let board = ChessBoard::from_fen("4k3/8/8/8/2b5/8/8/4K2R w K").unwrap();
+        assert!(board.legal_moves().all(|m| m.kind != MoveKind::KingsideCastle));
+    }
+
+    #[test]
+    fn squares() {
+        let e4: Square = "e4".parse().unwrap();
+        assert_eq!(e4.index(), 36);
+        assert_eq!((e4.file(), e4.rank()), (File::E, Rank::Fourth));
+        assert_eq!(Square::from_file_rank(File::A, Rank::Eighth), Square::new(0).unwrap());
+        assert_eq!("H1".parse::<Square>().unwrap().index(), 63);
+        assert_eq!(Square::new(63).unwrap().to_string(), "h1");
+        assert_eq!(Square::new(64), None);
+
+        for bad in ["", "e", "e44", "i1", "e9", "e0"] {
+            assert_eq!(bad.parse::<Square>(), Err(ParseSquareError));
+        }
+
+        let mut board = ChessBoard::new();
+        assert!(board.move_by_square(Square::from_file_rank(File::G, Rank::First), "f3".parse().unwrap()).is_ok());
+        assert_eq!(board.move_by_algebraic("e", "e5"), Err(MoveError::InvalidSquare));
+    }
+
+    #[test]
+    fn promotion() {
+        let mut board = setup(&[("e1", King, White), ("b7", Pawn, White), ("h8", King, Black)], true);
+        assert_eq!(board.promote(PromotionPiece::Queen), Err(MoveError::NoPromotionPending));
+        assert_eq!(board.move_by_algebraic("b7", "b8"), Ok(MoveOutcome::PromotionRequired));
+        assert!(board.can_promote());
+        assert_eq!(board.move_by_algebraic("h8", "h7"), Err(MoveError::PromotionPending));
+
+        assert_eq!(board.promote(PromotionPiece::Queen), Ok(MoveOutcome::Played(GameStatus::Check)));
+        assert_eq!(board.get_board()[1], Some((Queen, White)));
+        assert_eq!(board.status(), GameStatus::Check);
+
+        // Promoting together with the move.
+        let mut board = setup(&[("e1", King, White), ("b7", Pawn, White), ("h8", King, Black)], true);
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+        assert_eq!(board.move_with_promotion(sq("e1"), sq("e2"), PromotionPiece::Queen), Err(MoveError::InvalidPromotion));
+        assert_eq!(board.move_by_algebraic("b7", "b8x"), Err(MoveError::InvalidPromotion));
+        assert_eq!(board.move_by_algebraic("b7", "b8N"), Ok(MoveOutcome::Played(GameStatus::Draw(DrawReason::InsufficientMaterial))));
+        assert_eq!(board.piece_at(sq("b8")), Some((Knight, White)));
+        assert!(!board.can_promote());
+
+        board.undo();
+        assert_eq!(board.move_with_promotion(sq("b7"), sq("b8"), PromotionPiece::Rook), Ok(MoveOutcome::Played(GameStatus::Check)));
+    }
+
+    #[test]
+    fn undo_restores_captures_and_castling() {
+        let mut board = ChessBoard::new();
+        play(&mut board, &[("e2", "e4"), ("d7", "d5"), ("g1", "f3"), ("d5", "e4"), ("f1", "c4"), ("a7", "a6")]);
+        let before = board.get_board();
+
+        assert!(board.move_by_algebraic("e1", "g1").is_ok());
+        assert!(board.undo());
+        assert_eq!(board.get_board(), before);
+        assert!(board.castling_rights().white_kingside && board.castling_rights().white_queenside);
+
+        // Undo the capture on e4.
+        for _ in 0..3 { assert!(board.undo()); }
+        assert_eq!(board.get_board()[36], Some((Pawn, White)));
+        assert_eq!(board.get_board()[27], Some((Pawn, Black)));
+
+        // A new move clears the redo stack.
+        assert!(board.move_by_algebraic("b8", "c6").is_ok());
+        assert!(!board.redo());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+//! ECO classification of openings, e.g. "B90 Sicilian Defence: Najdorf Variation".
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::backend::play_long_algebraic;
+use crate::ChessBoard;
+
+/// Named opening from the Encyclopaedia of Chess Openings.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Opening {
+    /// ECO code, e.g. "B90".
+    pub eco: &'static str,
+    pub name: &'static str,
+    /// Moves leading to the opening from the start in long algebraic notation, e.g. "e2e4 c7c5".
+    pub moves: &'static str
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} {}", self.eco, self.name);
+    }
+}
+
+/// Openings known by `ChessBoard::current_opening()`. Variations come after the opening they belong to.
+pub const OPENINGS: &[Opening] = &[
+    Opening { eco: "A01", name: "Nimzo-Larsen Attack", moves: "b2b3" },
+    Opening { eco: "A02", name: "Bird's Opening", moves: "f2f4" },
+    Opening { eco: "A04", name: "Zukertort Opening", moves: "g1f3" },
+    Opening { eco: "A05", name: "Zukertort Opening: Quiet System", moves: "g1f3 g8f6" },
+    Opening { eco: "A06", name: "Zukertort Opening", moves: "g1f3 d7d5" },
+    Opening { eco: "A09", name: "Réti Opening", moves: "g1f3 d7d5 c2c4" },
+    Opening { eco: "A10", name: "English Opening", moves: "c2c4" },
+    Opening { eco: "A20", name: "English Opening: King's English Variation", moves: "c2c4 e7e5" },
+    Opening { eco: "A30", name: "English Opening: Symmetrical Variation", moves: "c2c4 c7c5" },
+    Opening { eco: "A40", name: "Queen's Pawn Game", moves: "d2d4" },
+    Opening { eco: "A43", name: "Benoni Defence: Old Benoni", moves: "d2d4 c7c5" },
+    Opening { eco: "A45", name: "Indian Defence", moves: "d2d4 g8f6" },
+    Opening { eco: "A46", name: "Indian Defence: Knights Variation", moves: "d2d4 g8f6 g1f3" },
+    Opening { eco: "A56", name: "Benoni Defence", moves: "d2d4 g8f6 c2c4 c7c5" },
+    Opening { eco: "A57", name: "Benko Gambit", moves: "d2d4 g8f6 c2c4 c7c5 d4d5 b7b5" },
+    Opening { eco: "A60", name: "Benoni Defence: Modern Variation", moves: "d2d4 g8f6 c2c4 c7c5 d4d5 e7e6" },
+    Opening { eco: "A80", name: "Dutch Defence", moves: "d2d4 f7f5" },
+    Opening { eco: "B00", name: "King's Pawn Opening", moves: "e2e4" },
+    Opening { eco: "B01", name: "Scandinavian Defence", moves: "e2e4 d7d5" },
+    Opening { eco: "B02", name: "Alekhine Defence", moves: "e2e4 g8f6" },
+    Opening { eco: "B06", name: "Modern Defence", moves: "e2e4 g7g6" },
+    Opening { eco: "B07", name: "Pirc Defence", moves: "e2e4 d7d6 d2d4 g8f6" },
+    Opening { eco: "B10", name: "Caro-Kann Defence", moves: "e2e4 c7c6" },
+    Opening { eco: "B12", name: "Caro-Kann Defence: Advance Variation", moves: "e2e4 c7c6 d2d4 d7d5 e4e5" },
+    Opening { eco: "B13", name: "Caro-Kann Defence: Exchange Variation", moves: "e2e4 c7c6 d2d4 d7d5 e4d5 c6d5" },
+    Opening { eco: "B18", name: "Caro-Kann Defence: Classical Variation", moves: "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5" },
+    Opening { eco: "B20", name: "Sicilian Defence", moves: "e2e4 c7c5" },
+    Opening { eco: "B21", name: "Sicilian Defence: Smith-Morra Gambit", moves: "e2e4 c7c5 d2d4 c5d4 c2c3" },
+    Opening { eco: "B22", name: "Sicilian Defence: Alapin Variation", moves: "e2e4 c7c5 c2c3" },
+    Opening { eco: "B23", name: "Sicilian Defence: Closed", moves: "e2e4 c7c5 b1c3" },
+    Opening { eco: "B27", name: "Sicilian Defence", moves: "e2e4 c7c5 g1f3" },
+    Opening { eco: "B30", name: "Sicilian Defence: Old Sicilian", moves: "e2e4 c7c5 g1f3 b8c6" },
+    Opening { eco: "B33", name: "Sicilian Defence: Sveshnikov Variation", moves: "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5" },
+    Opening { eco: "B40", name: "Sicilian Defence: French Variation", moves: "e2e4 c7c5 g1f3 e7e6" },
+    Opening { eco: "B50", name: "Sicilian Defence: Modern Variations", moves: "e2e4 c7c5 g1f3 d7d6" },
+    Opening { eco: "B54", name: "Sicilian Defence: Open", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4" },
+    Opening { eco: "B56", name: "Sicilian Defence: Open", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3" },
+    Opening { eco: "B56", name: "Sicilian Defence: Classical Variation", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 b8c6" },
+    Opening { eco: "B70", name: "Sicilian Defence: Dragon Variation", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 g7g6" },
+    Opening { eco: "B80", name: "Sicilian Defence: Scheveningen Variation", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e6" },
+    Opening { eco: "B90", name: "Sicilian Defence: Najdorf Variation", moves: "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6" },
+    Opening { eco: "C00", name: "French Defence", moves: "e2e4 e7e6" },
+    Opening { eco: "C01", name: "French Defence: Exchange Variation", moves: "e2e4 e7e6 d2d4 d7d5 e4d5" },
+    Opening { eco: "C02", name: "French Defence: Advance Variation", moves: "e2e4 e7e6 d2d4 d7d5 e4e5" },
+    Opening { eco: "C03", name: "French Defence: Tarrasch Variation", moves: "e2e4 e7e6 d2d4 d7d5 b1d2" },
+    Opening { eco: "C10", name: "French Defence: Paulsen Variation", moves: "e2e4 e7e6 d2d4 d7d5 b1c3" },
+    Opening { eco: "C11", name: "French Defence: Classical Variation", moves: "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6" },
+    Opening { eco: "C15", name: "French Defence: Winawer Variation", moves: "e2e4 e7e6 d2d4 d7d5 b1c3 f8b4" },
+    Opening { eco: "C20", name: "King's Pawn Game", moves: "e2e4 e7e5" },
+    Opening { eco: "C21", name: "Center Game", moves: "e2e4 e7e5 d2d4 e5d4" },
+    Opening { eco: "C23", name: "Bishop's Opening", moves: "e2e4 e7e5 f1c4" },
+    Opening { eco: "C25", name: "Vienna Game", moves: "e2e4 e7e5 b1c3" },
+    Opening { eco: "C30", name: "King's Gambit", moves: "e2e4 e7e5 f2f4" },
+    Opening { eco: "C33", name: "King's Gambit Accepted", moves: "e2e4 e7e5 f2f4 e5f4" },
+    Opening { eco: "C40", name: "King's Knight Opening", moves: "e2e4 e7e5 g1f3" },
+    Opening { eco: "C41", name: "Philidor Defence", moves: "e2e4 e7e5 g1f3 d7d6" },
+    Opening { eco: "C42", name: "Petrov's Defence", moves: "e2e4 e7e5 g1f3 g8f6" },
+    Opening { eco: "C44", name: "King's Knight Opening: Normal Variation", moves: "e2e4 e7e5 g1f3 b8c6" },
+    Opening { eco: "C44", name: "Scotch Game", moves: "e2e4 e7e5 g1f3 b8c6 d2d4" },
+    Opening { eco: "C45", name: "Scotch Game", moves: "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4" },
+    Opening { eco: "C46", name: "Three Knights Opening", moves: "e2e4 e7e5 g1f3 b8c6 b1c3" },
+    Opening { eco: "C47", name: "Four Knights Game", moves: "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6" },
+    Opening { eco: "C50", name: "Italian Game", moves: "e2e4 e7e5 g1f3 b8c6 f1c4" },
+    Opening { eco: "C50", name: "Italian Game: Giuoco Piano", moves: "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5" },
+    Opening { eco: "C51", name: "Italian Game: Evans Gambit", moves: "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 b2b4" },
+    Opening { eco: "C55", name: "Italian Game: Two Knights Defence", moves: "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6" },
+    Opening { eco: "C57", name: "Italian Game: Two Knights Defence, Knight Attack", moves: "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5" },
+    Opening { eco: "C60", name: "Ruy Lopez", moves: "e2e4 e7e5 g1f3 b8c6 f1b5" },
+    Opening { eco: "C65", name: "Ruy Lopez: Berlin Defence", moves: "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6" },
+    Opening { eco: "C68", name: "Ruy Lopez: Exchange Variation", moves: "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5c6" },
+    Opening { eco: "C70", name: "Ruy Lopez: Morphy Defence", moves: "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4" },
+    Opening { eco: "C80", name: "Ruy Lopez: Open Variation", moves: "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f6e4" },
+    Opening { eco: "C84", name: "Ruy Lopez: Closed", moves: "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7" },
+    Opening { eco: "D00", name: "Queen's Pawn Game", moves: "d2d4 d7d5" },
+    Opening { eco: "D02", name: "Queen's Pawn Game: London System", moves: "d2d4 d7d5 g1f3 g8f6 c1f4" },
+    Opening { eco: "D06", name: "Queen's Gambit", moves: "d2d4 d7d5 c2c4" },
+    Opening { eco: "D07", name: "Queen's Gambit Declined: Chigorin Defence", moves: "d2d4 d7d5 c2c4 b8c6" },
+    Opening { eco: "D08", name: "Queen's Gambit Declined: Albin Countergambit", moves: "d2d4 d7d5 c2c4 e7e5" },
+    Opening { eco: "D10", name: "Slav Defence", moves: "d2d4 d7d5 c2c4 c7c6" },
+    Opening { eco: "D20", name: "Queen's Gambit Accepted", moves: "d2d4 d7d5 c2c4 d5c4" },
+    Opening { eco: "D30", name: "Queen's Gambit Declined", moves: "d2d4 d7d5 c2c4 e7e6" },
+    Opening { eco: "D43", name: "Semi-Slav Defence", moves: "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 e7e6" },
+    Opening { eco: "D80", name: "Grünfeld Defence", moves: "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5" },
+    Opening { eco: "E00", name: "Indian Defence: East Indian Defence", moves: "d2d4 g8f6 c2c4 e7e6" },
+    Opening { eco: "E12", name: "Queen's Indian Defence", moves: "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6" },
+    Opening { eco: "E20", name: "Nimzo-Indian Defence", moves: "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4" },
+    Opening { eco: "E60", name: "King's Indian Defence", moves: "d2d4 g8f6 c2c4 g7g6" }
+];
+
+/// Get the opening reaching a position, keyed like `ChessBoard::placement_hash()`. The longest line wins if several reach it.
+pub(crate) fn by_position(key: u64) -> Option<Opening> {
+    static POSITIONS: OnceLock<HashMap<u64, Opening>> = OnceLock::new();
+
+    let positions = POSITIONS.get_or_init(|| {
+        let mut positions: HashMap<u64, Opening> = HashMap::new();
+        for &opening in OPENINGS {
+            let mut board = ChessBoard::new();
+            if !opening.moves.split(' ').all(|m| play_long_algebraic(&mut board, m)) { continue; }
+
+            let known = positions.entry(board.placement_hash()).or_insert(opening);
+            if opening.moves.len() > known.moves.len() { *known = opening; }
+        }
+        return positions;
+    });
+
+    return positions.get(&key).copied();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_is_playable() {
+        for &opening in OPENINGS {
+            let mut board = ChessBoard::new();
+            assert!(opening.moves.split(' ').all(|m| play_long_algebraic(&mut board, m)), "{}", opening);
+            assert_eq!(by_position(board.placement_hash()).map(|o| o.eco), Some(opening.eco), "{}", opening);
+        }
+    }
+}
@@ -0,0 +1,4 @@
+//! Board output formats besides text, see `ChessBoard::render()` for that.
+
+#[cfg(feature = "svg")]
+pub mod svg;
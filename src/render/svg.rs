@@ -0,0 +1,116 @@
+//! SVG images of the board, e.g. to embed positions in web pages and reports.
+
+use crate::{ChessBoard, Color, GameStatus, RenderOptions, Square};
+
+/// Size of a tile in pixels.
+const TILE: usize = 45;
+/// Width of the border holding the labels, when shown.
+const MARGIN: usize = 20;
+
+const LIGHT: &str = "#f0d9b5";
+const DARK: &str = "#b58863";
+const LAST_MOVE: &str = "#cdd26a";
+const CHECK: &str = "#e05050";
+
+/**
+Draw the board as an SVG image. The tiles of the last move are highlighted, and so is the king     <br/>
+when in check.                                                                                      <br/>
+Parameters:                                                                                         <br/>
+`board`: Board to draw                                                                              <br/>
+`options`: Only `coordinates` and `perspective` are used                                            <br/>
+Returns:                                                                                            <br/>
+The SVG document.
+*/
+pub fn render(board: &ChessBoard, options: &RenderOptions) -> String {
+    let margin = if options.coordinates { MARGIN } else { 0 };
+    let size = TILE * 8 + margin * 2;
+    let pieces = board.get_board();
+
+    let mut highlights: Vec<(Square, &str)> = vec![];
+    if let Some(m) = board.last_move() {
+        highlights.push((m.from, LAST_MOVE));
+        highlights.push((m.to, LAST_MOVE));
+    }
+    match board.status() {
+        GameStatus::Check | GameStatus::Checkmate(_) => {
+            let turn = if board.get_player() { Color::White } else { Color::Black };
+            highlights.push((board.king_square(turn), CHECK));
+        }
+        _ => { }
+    }
+
+    let mut out = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n", size);
+
+    for (i, piece) in pieces.iter().enumerate() {
+        let square = Square::new(i).unwrap();
+        let (x, y) = position(square, options.perspective, margin);
+        let light = (square.file().index() + square.rank().index()) % 2 == 1;
+        let fill = highlights.iter().rev().find(|h| h.0 == square).map_or(if light { LIGHT } else { DARK }, |h| h.1);
+
+        out.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{TILE}\" height=\"{TILE}\" fill=\"{}\"/>\n", x, y, fill));
+
+        if let Some((kind, color)) = *piece {
+            // The solid glyphs are used for both players so white pieces can be filled.
+            let (fill, stroke) = if color == Color::White { ("#fff", "#000") } else { ("#000", "#000") };
+            out.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\" stroke=\"{}\">{}</text>\n",
+                x + TILE / 2, y + TILE / 2, TILE * 4 / 5, fill, stroke, ChessBoard::glyph(kind, Color::Black)
+            ));
+        }
+    }
+
+    if options.coordinates {
+        for i in 0..8usize {
+            let file = Square::new(56 + i).unwrap();
+            let rank = Square::new(i * 8).unwrap();
+            let (fx, _) = position(file, options.perspective, margin);
+            let (_, ry) = position(rank, options.perspective, margin);
+
+            out.push_str(&format!("<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>\n",
+                fx + TILE / 2, size - MARGIN / 3, (b'a' + file.file().index() as u8) as char));
+            out.push_str(&format!("<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                MARGIN / 2, ry + TILE / 2, rank.rank().index() + 1));
+        }
+    }
+
+    out.push_str("</svg>\n");
+    return out;
+}
+
+/// Get the pixel position of the top left corner of a tile.
+fn position(square: Square, perspective: Color, margin: usize) -> (usize, usize) {
+    let (col, row) = match perspective {
+        Color::White => { (square.file().index(), 7 - square.rank().index()) }
+        Color::Black => { (7 - square.file().index(), square.rank().index()) }
+    };
+
+    return (margin + col * TILE, margin + row * TILE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pieces_and_highlights() {
+        let mut board = ChessBoard::new();
+        let svg = render(&board, &RenderOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+        assert_eq!(svg.matches("<text").count(), 32);
+        assert!(!svg.contains(LAST_MOVE));
+
+        for (f, t) in [("e2", "e4"), ("f7", "f6"), ("d1", "h5")] { board.move_by_algebraic(f, t).unwrap(); }
+        let svg = render(&board, &RenderOptions { coordinates: true, ..RenderOptions::default() });
+        assert_eq!(svg.matches(LAST_MOVE).count(), 2);
+        assert_eq!(svg.matches(CHECK).count(), 1);
+        assert_eq!(svg.matches("<text").count(), 32 + 16);
+    }
+
+    #[test]
+    fn orientation() {
+        let e1: Square = "e1".parse().unwrap();
+        assert_eq!(position(e1, Color::White, 0), (4 * TILE, 7 * TILE));
+        assert_eq!(position(e1, Color::Black, MARGIN), (MARGIN + 3 * TILE, MARGIN));
+    }
+}
@@ -0,0 +1,224 @@
+//! Games between two players, e.g. engines against each other or a bot against a human in tests.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::backend::{play_long_algebraic, with_promotion, SearchLimits};
+use crate::engine::eval::Evaluator;
+use crate::engine::{Engine, RandomPlayer};
+use crate::{ChessBoard, Color, DrawReason, GameStatus, Move};
+
+/// Half moves without a capture or pawn move after which the game is drawn.
+const FIFTY_MOVES: u32 = 100;
+
+/// Times a position must come up for the game to be drawn by repetition.
+const REPETITIONS: u32 = 3;
+
+/**
+Picks moves in a game run by `run()`. Implemented for the built-in engines, and for closures   <br/>
+like `|board: &ChessBoard, limits: &SearchLimits| ask_the_user(board)`, e.g. for a human.
+*/
+pub trait Player {
+    /**
+    Pick a move.                                                                            <br/>
+    Parameters:                                                                             <br/>
+    `board`: Position to move in                                                            <br/>
+    `limits`: Limits of the game, with the time left on the clocks if they are used         <br/>
+    Returns:                                                                                <br/>
+    A legal move, or `None` to resign. Pawns reaching the last rank promote to a queen if no piece is given.
+    */
+    fn play(&mut self, board: &ChessBoard, limits: &SearchLimits) -> Option<Move>;
+}
+
+impl<E: Evaluator> Player for Engine<E> {
+    fn play(&mut self, board: &ChessBoard, limits: &SearchLimits) -> Option<Move> {
+        return self.think(board, limits, None).best_move;
+    }
+}
+
+impl Player for RandomPlayer {
+    fn play(&mut self, board: &ChessBoard, _limits: &SearchLimits) -> Option<Move> { return self.choose(board); }
+}
+
+impl<F: FnMut(&ChessBoard, &SearchLimits) -> Option<Move>> Player for F {
+    fn play(&mut self, board: &ChessBoard, limits: &SearchLimits) -> Option<Move> { return self(board, limits); }
+}
+
+/// Why a game run by `run()` ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// Neither side has enough pieces left to checkmate.
+    InsufficientMaterial,
+    /// Fifty moves by each side without a capture or pawn move.
+    FiftyMoves,
+    /// The same position came up three times.
+    Repetition,
+    /// A player used up the time on their clock. It's a draw if the opponent has no mating material.
+    Timeout,
+    /// A player resigned or tried an illegal move.
+    Forfeit,
+    /// Both players agreed to a draw, e.g. when starting from a board with an accepted offer.
+    Agreement
+}
+
+/// A finished game.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// Position the game started from.
+    pub start: ChessBoard,
+    /// Moves played from `start`, with the promotion piece where a pawn promoted.
+    pub moves: Vec<Move>,
+    /// Winner, or `None` for a draw.
+    pub winner: Option<Color>,
+    pub termination: Termination,
+    /// Position the game ended in.
+    pub board: ChessBoard
+}
+
+impl GameRecord {
+    /// Get the result as written in PGN: "1-0", "0-1" or "1/2-1/2".
+    pub fn result(&self) -> &'static str {
+        return match self.winner {
+            Some(Color::White) => { "1-0" }
+            Some(Color::Black) => { "0-1" }
+            None => { "1/2-1/2" }
+        };
+    }
+}
+
+/**
+Play a game between two players from the starting position until it ends.                     <br/>
+Parameters:                                                                                    <br/>
+`white`: Player of the white pieces                                                            <br/>
+`black`: Player of the black pieces                                                            <br/>
+`limits`: Passed to the players. If `time` is given for a side, its clock runs while it thinks   <br/>
+and gets the increment after each move. Running out of time loses, or draws if the opponent      <br/>
+has no mating material. `moves_to_go` is ignored.                                               <br/>
+Returns:                                                                                       <br/>
+The game. Besides checkmate, stalemate and insufficient material, it ends drawn after fifty     <br/>
+moves without a capture or pawn move or the third repetition of a position.
+*/
+pub fn run(white: impl Player, black: impl Player, limits: &SearchLimits) -> GameRecord {
+    return run_from(&ChessBoard::new(), white, black, limits);
+}
+
+/**
+Play a game like `run()` from another position, e.g. after an opening from a test suite.      <br/>
+Parameters:                                                                                    <br/>
+`start`: Position to start from. Positions before it don't count for repetitions.             <br/>
+`white`: Player of the white pieces                                                            <br/>
+`black`: Player of the black pieces                                                            <br/>
+`limits`: Passed to the players, with the clocks run like in `run()`                          <br/>
+Returns:                                                                                       <br/>
+The game.
+*/
+pub fn run_from(start: &ChessBoard, mut white: impl Player, mut black: impl Player, limits: &SearchLimits) -> GameRecord {
+    let mut board = start.clone();
+    let mut moves = vec![];
+    let mut clocks = limits.time;
+    let mut seen: HashMap<u64, u32> = HashMap::from([(board.zobrist_hash(), 1)]);
+
+    let (winner, termination) = loop {
+        let color = if board.get_player() { Color::White } else { Color::Black };
+        match board.status() {
+            GameStatus::Checkmate(winner) => { break (Some(winner), Termination::Checkmate); }
+            GameStatus::Stalemate => { break (None, Termination::Stalemate); }
+            GameStatus::Timeout(winner) => { break (Some(winner), Termination::Timeout); }
+            GameStatus::Draw(DrawReason::Timeout) => { break (None, Termination::Timeout); }
+            GameStatus::Draw(DrawReason::InsufficientMaterial) => { break (None, Termination::InsufficientMaterial); }
+            GameStatus::Draw(DrawReason::Agreement) => { break (None, Termination::Agreement); }
+            GameStatus::Ongoing | GameStatus::Check => { }
+        }
+        if board.halfmove_clock() >= FIFTY_MOVES { break (None, Termination::FiftyMoves); }
+
+        let player: &mut dyn Player = if color == Color::White { &mut white } else { &mut black };
+        let start = Instant::now();
+        let chosen = player.play(&board, &SearchLimits { time: clocks, ..*limits });
+        let elapsed = start.elapsed();
+
+        // A move chosen after the flag fell isn't played.
+        if let Some(left) = clocks[color as usize] {
+            // Losing on time is a draw if the opponent couldn't have won.
+            let winner = color.opposite();
+            if elapsed > left { break (Some(winner).filter(|w| board.has_mating_material(*w)), Termination::Timeout); }
+            clocks[color as usize] = Some(left - elapsed + limits.increment[color as usize]);
+        }
+
+        let Some(m) = chosen.map(|m| with_promotion(&board, m)) else { break (Some(color.opposite()), Termination::Forfeit); };
+        if !play_long_algebraic(&mut board, &m.to_string()) { break (Some(color.opposite()), Termination::Forfeit); }
+        moves.push(m);
+
+        let count = seen.entry(board.zobrist_hash()).or_insert(0);
+        *count += 1;
+        if *count >= REPETITIONS { break (None, Termination::Repetition); }
+    };
+
+    return GameRecord { start: start.clone(), moves, winner, termination, board };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn random_games() {
+        let limits = SearchLimits::default();
+        for seed in 0..5 {
+            let game = run(RandomPlayer::new(seed), RandomPlayer::new(seed + 100), &limits);
+            assert_eq!(run(RandomPlayer::new(seed), RandomPlayer::new(seed + 100), &limits).moves, game.moves);
+            assert_ne!(game.termination, Termination::Forfeit);
+            assert_eq!(game.winner.is_some(), game.termination == Termination::Checkmate);
+
+            // The record replays to the final position.
+            let mut board = game.start.clone();
+            for m in &game.moves { assert!(play_long_algebraic(&mut board, &m.to_string())); }
+            assert_eq!(board, game.board);
+        }
+    }
+
+    #[test]
+    fn engine_and_callbacks() {
+        let mut engine = Engine::default();
+        engine.depth = 2;
+        let game = run(engine, RandomPlayer::new(3), &SearchLimits::default());
+        assert_eq!((game.result(), game.termination), ("1-0", Termination::Checkmate));
+
+        // Resigning, illegal moves and slow moves lose.
+        let first = |board: &ChessBoard, _: &SearchLimits| board.legal_moves().next();
+        let game = run(first, |_: &ChessBoard, _: &SearchLimits| None, &SearchLimits::default());
+        assert_eq!((game.winner, game.termination, game.moves.len()), (Some(Color::White), Termination::Forfeit, 1));
+
+        let illegal = |board: &ChessBoard, _: &SearchLimits| board.legal_moves().next().map(|m| Move { to: "e5".parse().unwrap(), ..m });
+        assert_eq!(run(illegal, first, &SearchLimits::default()).result(), "0-1");
+
+        let slow = |board: &ChessBoard, limits: &SearchLimits| {
+            assert!(limits.time[0].is_some());
+            thread::sleep(Duration::from_millis(40));
+            return board.legal_moves().next();
+        };
+        let limits = SearchLimits { time: [Some(Duration::from_millis(100)), None], ..SearchLimits::default() };
+        let game = run(slow, first, &limits);
+        assert_eq!((game.winner, game.termination), (Some(Color::Black), Termination::Timeout));
+        // However long the moves took, the record ends before the move white made too late.
+        assert!(game.moves.len() % 2 == 0 && game.board.get_player());
+
+        // A game agreed drawn before it starts isn't taken for a lack of material.
+        let mut board = ChessBoard::new();
+        assert!(board.offer_draw(Color::White) && board.accept_draw());
+        let game = run_from(&board, first, first, &SearchLimits::default());
+        assert_eq!((game.result(), game.termination, game.moves.len()), ("1/2-1/2", Termination::Agreement, 0));
+
+        // From a position, black to move is mated at once.
+        let mut board = ChessBoard::new();
+        for m in ["f2f3", "e7e5", "g2g4"] { play_long_algebraic(&mut board, m); }
+        let mut engine = Engine::default();
+        engine.depth = 1;
+        let game = run_from(&board, first, engine, &SearchLimits::default());
+        assert_eq!((game.result(), game.moves.len(), game.board.fullmove_number()), ("0-1", 1, 3));
+    }
+}
@@ -0,0 +1,149 @@
+//! Matches between two players, e.g. engine settings, with the Elo difference they show.
+
+use std::fmt;
+
+use crate::backend::SearchLimits;
+use crate::selfplay::{run_from, Player};
+use crate::{ChessBoard, Color};
+
+/// Normal quantile for a 95% confidence interval.
+const CONFIDENCE_95: f64 = 1.96;
+
+/// Games won, drawn and lost by the first player of a match.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32
+}
+
+impl MatchResult {
+    /// Get the number of games played.
+    pub fn games(&self) -> u32 { return self.wins + self.draws + self.losses; }
+
+    /// Get the share of the points won by the first player, from 0 to 1. A draw is half a point.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 { return 0.5; }
+        return (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64;
+    }
+
+    /**
+    Estimate how much stronger the first player is.                                          <br/>
+    Returns:                                                                                 <br/>
+    The Elo difference and the margin of its 95% confidence interval, e.g. (45.0, 120.0) for  <br/>
+    45 ± 120. They are infinite when a bound of the interval reaches all wins or all losses,  <br/>
+    which takes more games to narrow down.
+    */
+    pub fn elo(&self) -> (f64, f64) {
+        let n = self.games() as f64;
+        let score = self.score();
+        if n == 0.0 { return (0.0, f64::INFINITY); }
+
+        // Spread of the points per game, over the games played.
+        let variance = (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2)) / n;
+        let margin = CONFIDENCE_95 * (variance / n).sqrt();
+        let (low, high) = (elo_difference(score - margin), elo_difference(score + margin));
+
+        if !low.is_finite() || !high.is_finite() { return (elo_difference(score), f64::INFINITY); }
+        return (elo_difference(score), (high - low) / 2.0);
+    }
+}
+
+impl fmt::Display for MatchResult {
+    /// Like "+3 =4 -1, Elo +45 ± 120".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (elo, margin) = self.elo();
+        return write!(f, "+{} ={} -{}, Elo {:+.0} ± {:.0}", self.wins, self.draws, self.losses, elo, margin);
+    }
+}
+
+/// Get the Elo difference expected to give a score, from 0 to 1. Infinite for all wins or losses.
+fn elo_difference(score: f64) -> f64 {
+    if score <= 0.0 { return f64::NEG_INFINITY; }
+    if score >= 1.0 { return f64::INFINITY; }
+    return 400.0 * (score / (1.0 - score)).log10();
+}
+
+/**
+Play a match between two players, switching colors every game. With an opening suite, each    <br/>
+opening is played twice, once with each player as white, so neither gets the better side.    <br/>
+Parameters:                                                                                   <br/>
+`first`: Makes the first player for each game, e.g. `|| Engine::default()`                    <br/>
+`second`: Makes the second player for each game                                                <br/>
+`games`: Number of games                                                                      <br/>
+`openings`: Positions to start the games from in turn, or empty to start from the beginning  <br/>
+`limits`: Limits of every game, see `selfplay::run()`                                          <br/>
+Returns:                                                                                      <br/>
+The games won, drawn and lost by the first player.
+*/
+pub fn play_match<A: Player, B: Player>(mut first: impl FnMut() -> A, mut second: impl FnMut() -> B, games: u32, openings: &[ChessBoard], limits: &SearchLimits) -> MatchResult {
+    let mut result = MatchResult::default();
+    let start = ChessBoard::new();
+
+    for game in 0..games {
+        let board = if openings.is_empty() { &start } else { &openings[(game / 2) as usize % openings.len()] };
+        let first_color = if game % 2 == 0 { Color::White } else { Color::Black };
+        let record = match first_color {
+            Color::White => { run_from(board, first(), second(), limits) }
+            Color::Black => { run_from(board, second(), first(), limits) }
+        };
+
+        match record.winner {
+            Some(c) if c == first_color => { result.wins += 1; }
+            Some(_) => { result.losses += 1; }
+            None => { result.draws += 1; }
+        }
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use crate::Move;
+    use crate::backend::play_long_algebraic;
+    use crate::engine::{Engine, RandomPlayer};
+
+    #[test]
+    fn elo() {
+        let result = MatchResult { wins: 30, draws: 40, losses: 10 };
+        let (elo, margin) = result.elo();
+        assert_eq!(result.score(), 0.625);
+        assert!((elo - 88.7).abs() < 0.1 && (margin - 55.0).abs() < 5.0, "{elo} {margin}");
+        assert_eq!(MatchResult { wins: 3, draws: 4, losses: 3 }.to_string(), "+3 =4 -3, Elo +0 ± 182");
+
+        // Twice the games narrow the margin by about the square root of two.
+        let twice = MatchResult { wins: 60, draws: 80, losses: 20 }.elo();
+        assert!((twice.0 - elo).abs() < 0.001 && (margin / twice.1 - 2f64.sqrt()).abs() < 0.05);
+
+        assert_eq!(MatchResult { wins: 4, draws: 0, losses: 0 }.elo(), (f64::INFINITY, f64::INFINITY));
+        assert_eq!(MatchResult::default().elo(), (0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn matches() {
+        let limits = SearchLimits::default();
+        let mut seed = 0;
+        let mut random = || { seed += 1; return RandomPlayer::new(seed); };
+        let engine = || { let mut e = Engine::default(); e.depth = 1; return e; };
+
+        let result = play_match(engine, &mut random, 4, &[], &limits);
+        assert_eq!(result.games(), 4);
+        assert!(result.wins >= 3 && result.losses == 0, "{result}");
+
+        // Both players get each opening once as white. Here the second resigns as soon as it is on the move.
+        let mut opening = ChessBoard::new();
+        play_long_algebraic(&mut opening, "e2e4");
+        let resigned = &RefCell::new(vec![]);
+        let first = || |board: &ChessBoard, _: &SearchLimits| board.legal_moves().next();
+        let resign = || move |board: &ChessBoard, _: &SearchLimits| -> Option<Move> {
+            resigned.borrow_mut().push((board.get_player(), board.fullmove_number()));
+            return None;
+        };
+        let result = play_match(first, resign, 2, &[opening], &limits);
+        assert_eq!(*resigned.borrow(), [(false, 1), (true, 2)]);
+        assert_eq!(result, MatchResult { wins: 2, draws: 0, losses: 0 });
+    }
+}
@@ -0,0 +1,243 @@
+//! Engine side of the UCI protocol, so a search can be used from GUIs like Arena or Cute Chess.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::backend::{parse_fen, play_long_algebraic, with_promotion, EngineBackend, SearchLimits};
+use crate::{ChessBoard, Color};
+
+/**
+UCI adapter around a search. It keeps the position set by the GUI and runs searches on     <br/>
+their own thread, so `stop` and `isready` are answered while searching.
+*/
+pub struct Uci<B: EngineBackend + Send + 'static> {
+    board: ChessBoard,
+    /// The backend, or `None` while it is searching.
+    backend: Option<B>,
+    /// Running search, handing back the backend when done.
+    search: Option<JoinHandle<B>>,
+    stop: Arc<AtomicBool>
+}
+
+impl<B: EngineBackend + Send + 'static> Uci<B> {
+    /// Get a new adapter around a backend.
+    pub fn new(backend: B) -> Uci<B> {
+        return Uci { board: ChessBoard::new(), backend: Some(backend), search: None, stop: Arc::new(AtomicBool::new(false)) };
+    }
+
+    /**
+    Answer UCI commands until `quit` or the end of the input, e.g. from stdin to stdout.   <br/>
+    Parameters:                                                                             <br/>
+    `input`: Commands from the GUI, one per line                                            <br/>
+    `output`: Where to write the answers                                                    <br/>
+    Returns:                                                                                <br/>
+    An error if reading or writing failed.
+    */
+    pub fn run<R: BufRead, W: Write + Send + 'static>(&mut self, input: R, output: W) -> io::Result<()> {
+        let output = Arc::new(Mutex::new(output));
+
+        for line in input.lines() {
+            if !self.command(&line?, &output)? { break; }
+        }
+
+        self.stop_search();
+        return Ok(());
+    }
+
+    /// Handle one command. Returns `false` on `quit`.
+    fn command<W: Write + Send + 'static>(&mut self, line: &str, output: &Arc<Mutex<W>>) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let out = |text: String| -> io::Result<()> {
+            let mut o = output.lock().unwrap();
+            writeln!(o, "{}", text)?;
+            return o.flush();
+        };
+
+        match words.first().copied() {
+            Some("uci") => {
+                let backend = self.backend();
+                out(format!("id name {}", backend.name()))?;
+                for o in backend.options() {
+                    out(format!("option name {} type spin default {} min {} max {}", o.name, o.default, o.min, o.max))?;
+                }
+                out("uciok".to_string())?;
+            }
+            Some("isready") => { out("readyok".to_string())?; }
+            Some("setoption") => {
+                // Option names may contain spaces: "setoption name Skill Level value 5".
+                let name_at = words.iter().position(|w| *w == "name").map_or(words.len(), |i| i + 1);
+                let value_at = words.iter().position(|w| *w == "value").unwrap_or(words.len());
+                let name = words[name_at.min(value_at)..value_at].join(" ");
+                let value = words.get(value_at + 1..).map_or(String::new(), |v| v.join(" "));
+                self.backend().set_option(&name, &value);
+            }
+            Some("ucinewgame") => {
+                self.backend().new_game();
+                self.board = ChessBoard::new();
+            }
+            Some("position") => {
+                if let Some(bad) = self.set_position(&words[1..]) { out(format!("info string invalid {}", bad))?; }
+            }
+            Some("go") => { self.go(&words[1..], output); }
+            Some("stop") => { self.stop_search(); }
+            Some("quit") => {
+                self.stop_search();
+                return Ok(false);
+            }
+            _ => { }
+        }
+
+        return Ok(true);
+    }
+
+    /// Get the backend, stopping a running search, which then reports its best move. A "go infinite" would never finish otherwise.
+    fn backend(&mut self) -> &mut B {
+        if let Some(search) = self.search.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            self.backend = search.join().ok();
+        }
+
+        return self.backend.as_mut().expect("the backend is lost only if a search panicked");
+    }
+
+    /// Stop a running search, which then reports its best move.
+    fn stop_search(&mut self) {
+        self.backend();
+    }
+
+    /// Set up the position from "startpos" or "fen ...", followed by "moves ...". Returns what was invalid, if anything.
+    fn set_position(&mut self, words: &[&str]) -> Option<String> {
+        let moves_at = words.iter().position(|w| *w == "moves").unwrap_or(words.len());
+
+        self.board = match words.first().copied() {
+            Some("startpos") => { ChessBoard::new() }
+            Some("fen") => {
+                match parse_fen(&words[1..moves_at]) {
+                    Some(b) => { b }
+                    None => { return Some(format!("fen {}", words[1..moves_at].join(" "))); }
+                }
+            }
+            _ => { return Some("position".to_string()); }
+        };
+
+        for m in words.iter().skip(moves_at + 1) {
+            if !play_long_algebraic(&mut self.board, m) {
+                return Some(format!("move {}", m));
+            }
+        }
+
+        return None;
+    }
+
+    /// Start searching the current position on a new thread, which writes "bestmove" when done.
+    fn go<W: Write + Send + 'static>(&mut self, words: &[&str], output: &Arc<Mutex<W>>) {
+        let limits = parse_go(words);
+        let board = self.board.clone();
+        self.backend();
+        let mut backend = self.backend.take().expect("the backend is lost only if a search panicked");
+
+        self.stop = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&self.stop);
+        let output = Arc::clone(output);
+
+        self.search = Some(std::thread::spawn(move || {
+            let best = backend.search(&board, &limits, &stop);
+
+            // "go infinite" may only report its move once stopped.
+            while limits.infinite && !stop.load(Ordering::Relaxed) { std::thread::sleep(Duration::from_millis(1)); }
+
+            let text = match best {
+                Some(m) => { format!("bestmove {}", with_promotion(&board, m)) }
+                None => { "bestmove 0000".to_string() }
+            };
+            if let Ok(mut o) = output.lock() {
+                let _ = writeln!(o, "{}", text);
+                let _ = o.flush();
+            }
+
+            return backend;
+        }));
+    }
+}
+
+/// Read the limits of a "go" command.
+fn parse_go(words: &[&str]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let ms = |i: usize| words.get(i + 1).and_then(|w| w.parse::<u64>().ok()).map(Duration::from_millis);
+
+    for (i, w) in words.iter().enumerate() {
+        match *w {
+            "depth" => { limits.depth = words.get(i + 1).and_then(|d| d.parse().ok()); }
+            "nodes" => { limits.nodes = words.get(i + 1).and_then(|n| n.parse().ok()); }
+            "movetime" => { limits.movetime = ms(i); }
+            "wtime" => { limits.time[Color::White as usize] = ms(i); }
+            "btime" => { limits.time[Color::Black as usize] = ms(i); }
+            "winc" => { limits.increment[Color::White as usize] = ms(i).unwrap_or_default(); }
+            "binc" => { limits.increment[Color::Black as usize] = ms(i).unwrap_or_default(); }
+            "movestogo" => { limits.moves_to_go = words.get(i + 1).and_then(|n| n.parse().ok()); }
+            "infinite" => { limits.infinite = true; }
+            _ => { }
+        }
+    }
+
+    return limits;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::testing::{First, Shared};
+    use crate::{Move, PieceKind};
+
+    fn session(input: &str) -> Vec<String> {
+        let output = Shared::default();
+        Uci::new(First).run(input.as_bytes(), output.clone()).unwrap();
+        return output.lines();
+    }
+
+    #[test]
+    fn handshake_and_search() {
+        assert_eq!(session("uci\nisready\n"), ["id name First", "uciok", "readyok"]);
+        assert_eq!(session("position startpos\ngo depth 1\n"), ["bestmove a2a4"]);
+        assert_eq!(session("position startpos moves a2a4 e7e5\ngo movetime 10\n"), ["bestmove a4a5"]);
+        assert_eq!(session("position startpos moves e2e5\n"), ["info string invalid move e2e5"]);
+        assert_eq!(session("go infinite\nstop\nquit\n"), ["bestmove a2a4"]);
+
+        // Commands that need the backend stop an infinite search instead of waiting for it forever.
+        assert_eq!(session("go infinite\nsetoption name Hash value 1\nisready\n"), ["bestmove a2a4", "readyok"]);
+        assert_eq!(session("go infinite\nuci\n"), ["bestmove a2a4", "id name First", "uciok"]);
+        assert_eq!(session("go infinite\nucinewgame\ngo infinite\nstop\n"), ["bestmove a2a4", "bestmove a2a4"]);
+    }
+
+    #[test]
+    fn fen_positions() {
+        // The pawn on b7 promotes, and the answer names the piece.
+        assert_eq!(session("position fen 7k/1P6/8/8/8/8/8/K7 w - - 0 1\ngo\n"), ["bestmove b7b8q"]);
+        assert_eq!(session("position fen 7k/1P6/8/8/8/8/8/K7 w - - 0 1 moves a1b1 h8g8\ngo\n"), ["bestmove b7b8q"]);
+        assert_eq!(session("position fen 8/8/8/8/8/8/8/K7 w - -\n"), ["info string invalid fen 8/8/8/8/8/8/8/K7 w - -"]);
+
+        // A promotion without the piece is rejected, and the board isn't left waiting for one.
+        let fen = "position fen 7k/4P3/8/8/8/8/8/4K3 w - - 0 1 moves e1d1 h8g8";
+        assert_eq!(session(&format!("{fen} e7e8\ngo\n")), ["info string invalid move e7e8", "bestmove e7e8q"]);
+        assert_eq!(session(&format!("{fen} e7e8q g8h7\ngo\n")), ["bestmove e8a8"]);
+
+        // Black just played d7d5, so the first legal move is taking it en passant.
+        assert_eq!(session("position fen 4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2\ngo\n"), ["bestmove e5d6"]);
+        assert_eq!(session("position fen 4k3/8/8/3pP3/8/8/8/4K3 w - - 0 2\ngo\n"), ["bestmove e5e6"]);
+        let board = parse_fen(&["7k/1P6/8/8/8/8/8/K7", "w"]).unwrap();
+        let m = board.legal_moves().find(|m| m.to.to_string() == "b8").unwrap();
+        assert_eq!(with_promotion(&board, m).to_string(), "b7b8q");
+        assert_eq!(with_promotion(&board, Move { promotion: Some(PieceKind::Knight), ..m }).to_string(), "b7b8n");
+    }
+
+    #[test]
+    fn go_limits() {
+        let limits = parse_go(&["wtime", "1000", "btime", "2000", "winc", "10", "movestogo", "5", "depth", "3"]);
+        assert_eq!(limits.time, [Some(Duration::from_millis(1000)), Some(Duration::from_millis(2000))]);
+        assert_eq!(limits.increment, [Duration::from_millis(10), Duration::ZERO]);
+        assert_eq!((limits.moves_to_go, limits.depth, limits.infinite), (Some(5), Some(3), false));
+    }
+}
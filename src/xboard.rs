@@ -0,0 +1,280 @@
+//! Engine side of the CECP (xboard) protocol, for older GUIs and tournament managers like XBoard and WinBoard.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::backend::{parse_fen, play_long_algebraic, with_promotion, EngineBackend, SearchLimits};
+use crate::{ChessBoard, Color, DrawReason, GameStatus, Move};
+
+/// Commands that are understood but need no answer.
+const IGNORED: [&str; 13] = ["xboard", "accepted", "rejected", "random", "post", "nopost", "hard", "easy", "computer", "name", "rating", "ics", "draw"];
+
+/**
+CECP adapter around a search. Unlike UCI, the adapter keeps the game itself: the GUI sends     <br/>
+single moves and the engine answers with its own whenever it is on the move.
+*/
+pub struct XBoard<B: EngineBackend + Send + 'static> {
+    board: ChessBoard,
+    /// Side the engine plays, or `None` in force mode.
+    engine: Option<Color>,
+    /// Depth, time per move and increment set by the GUI. The clocks are added when searching.
+    limits: SearchLimits,
+    /// Moves per time control from "level", or `None` if the whole game is one control.
+    moves_per_session: Option<u32>,
+    /// Clocks of the engine and the opponent from "time" and "otim".
+    clocks: [Option<Duration>; 2],
+    /// The backend, or `None` while it is searching.
+    backend: Option<B>,
+    /// Running search, handing back the backend and the move it played.
+    search: Option<JoinHandle<(B, Option<Move>)>>,
+    stop: Arc<AtomicBool>
+}
+
+impl<B: EngineBackend + Send + 'static> XBoard<B> {
+    /// Get a new adapter around a backend. It plays black until told otherwise, like after "new".
+    pub fn new(backend: B) -> XBoard<B> {
+        return XBoard {
+            board: ChessBoard::new(),
+            engine: Some(Color::Black),
+            limits: SearchLimits::default(),
+            moves_per_session: None,
+            clocks: [None; 2],
+            backend: Some(backend),
+            search: None,
+            stop: Arc::new(AtomicBool::new(false))
+        };
+    }
+
+    /**
+    Answer CECP commands until `quit` or the end of the input, e.g. from stdin to stdout.  <br/>
+    Parameters:                                                                             <br/>
+    `input`: Commands from the GUI, one per line                                            <br/>
+    `output`: Where to write the answers                                                    <br/>
+    Returns:                                                                                <br/>
+    An error if reading or writing failed.
+    */
+    pub fn run<R: BufRead, W: Write + Send + 'static>(&mut self, input: R, output: W) -> io::Result<()> {
+        let output = Arc::new(Mutex::new(output));
+
+        for line in input.lines() {
+            if !self.command(&line?, &output)? { break; }
+        }
+
+        self.stop_search();
+        return Ok(());
+    }
+
+    /// Handle one command. Returns `false` on `quit`.
+    fn command<W: Write + Send + 'static>(&mut self, line: &str, output: &Arc<Mutex<W>>) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let out = |text: String| -> io::Result<()> {
+            let mut o = output.lock().unwrap();
+            writeln!(o, "{}", text)?;
+            return o.flush();
+        };
+        let number = |i: usize| words.get(i).and_then(|w| w.parse::<u64>().ok());
+
+        // "?" asks for the move right away. Any other command waits for the engine to make its move first.
+        if words.first() == Some(&"?") || words.first() == Some(&"quit") { self.stop.store(true, Ordering::Relaxed); }
+        self.finish_search();
+
+        match words.first().copied() {
+            None | Some("?") => { }
+            Some("protover") => {
+                let backend = self.backend();
+                out(format!("feature myname=\"{}\" setboard=1 usermove=1 ping=1 playother=1 sigint=0 sigterm=0 colors=0 analyze=0 reuse=1 done=0", backend.name()))?;
+                for o in backend.options() {
+                    out(format!("feature option=\"{} -spin {} {} {}\"", o.name, o.default, o.min, o.max))?;
+                }
+                out("feature done=1".to_string())?;
+            }
+            Some("new") => {
+                self.backend().new_game();
+                self.board = ChessBoard::new();
+                self.engine = Some(Color::Black);
+                self.limits.depth = None;
+            }
+            Some("force") | Some("result") => { self.engine = None; }
+            Some("go") => {
+                self.engine = Some(self.side_to_move());
+                self.think(output);
+            }
+            Some("playother") => { self.engine = Some(self.side_to_move().opposite()); }
+            Some("usermove") => {
+                let m = words.get(1).copied().unwrap_or("");
+                if !play_long_algebraic(&mut self.board, m) {
+                    out(format!("Illegal move: {}", m))?;
+                } else if self.engine == Some(self.side_to_move()) {
+                    self.think(output);
+                }
+            }
+            Some("setboard") => {
+                match parse_fen(&words[1..]) {
+                    Some(b) => { self.board = b; }
+                    None => { out("tellusererror Illegal position".to_string())?; }
+                }
+            }
+            Some("undo") => { self.board.undo(); }
+            Some("remove") => {
+                self.board.undo();
+                self.board.undo();
+            }
+            Some("level") => {
+                if !self.set_level(&words[1..]) { out(format!("Error (invalid time control): {}", line))?; }
+            }
+            Some("st") => { self.limits.movetime = number(1).map(Duration::from_secs); }
+            Some("sd") => { self.limits.depth = number(1).map(|d| d as u32); }
+            Some("time") => { self.clocks[0] = number(1).map(|cs| Duration::from_millis(cs * 10)); }
+            Some("otim") => { self.clocks[1] = number(1).map(|cs| Duration::from_millis(cs * 10)); }
+            Some("ping") => { out(format!("pong {}", words.get(1).unwrap_or(&"")))?; }
+            Some("option") => {
+                // "option Hash=64", where names may contain spaces.
+                let setting = line.trim_start()["option".len()..].trim();
+                let (name, value) = setting.split_once('=').unwrap_or((setting, ""));
+                self.backend().set_option(name, value);
+            }
+            Some("quit") => { return Ok(false); }
+            Some(command) => {
+                if !IGNORED.contains(&command) { out(format!("Error (unknown command): {}", command))?; }
+            }
+        }
+
+        return Ok(true);
+    }
+
+    /// Get the color of the side to move.
+    fn side_to_move(&self) -> Color {
+        return if self.board.get_player() { Color::White } else { Color::Black };
+    }
+
+    /// Get the backend, waiting for a running search to finish.
+    fn backend(&mut self) -> &mut B {
+        self.finish_search();
+        return self.backend.as_mut().expect("the backend is lost only if a search panicked");
+    }
+
+    /// Wait for a running search and play the move it found.
+    fn finish_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            if let Ok((backend, best)) = search.join() {
+                self.backend = Some(backend);
+                if let Some(m) = best { play_long_algebraic(&mut self.board, &m.to_string()); }
+            }
+        }
+    }
+
+    /// Stop a running search, which then plays its best move.
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.finish_search();
+    }
+
+    /**
+    Read "level MPS BASE INC": moves per time control, base time in minutes or minutes:seconds   <br/>
+    and increment in seconds. Returns `false` if the command is malformed.
+    */
+    fn set_level(&mut self, words: &[&str]) -> bool {
+        let base_valid = words.get(1).is_some_and(|base| {
+            let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+            return minutes.parse::<u64>().is_ok() && seconds.parse::<u64>().is_ok();
+        });
+        let (Some(moves), Some(increment), true) = (
+            words.first().and_then(|w| w.parse::<u32>().ok()),
+            words.get(2).and_then(|w| w.parse::<f64>().ok()).and_then(|s| Duration::try_from_secs_f64(s).ok()),
+            base_valid
+        ) else { return false; };
+
+        self.moves_per_session = if moves == 0 { None } else { Some(moves) };
+        self.limits.increment = [increment; 2];
+        self.limits.movetime = None;
+        return true;
+    }
+
+    /// Start searching the current position on a new thread, which writes the engine's move and the result if the game ended.
+    fn think<W: Write + Send + 'static>(&mut self, output: &Arc<Mutex<W>>) {
+        let mut limits = self.limits;
+        let engine = self.side_to_move();
+        limits.time[engine as usize] = self.clocks[0];
+        limits.time[engine.opposite() as usize] = self.clocks[1];
+        limits.moves_to_go = self.moves_per_session.map(|mps| mps - (self.board.fullmove_number() - 1) % mps);
+
+        let mut board = self.board.clone();
+        let mut backend = self.backend.take().expect("the backend is lost only if a search panicked");
+        self.stop = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&self.stop);
+        let output = Arc::clone(output);
+
+        self.search = Some(std::thread::spawn(move || {
+            let best = backend.search(&board, &limits, &stop).map(|m| with_promotion(&board, m));
+
+            let mut lines = vec![];
+            if let Some(m) = best {
+                lines.push(format!("move {}", m));
+                play_long_algebraic(&mut board, &m.to_string());
+            }
+            lines.extend(result(board.status()));
+            if let Ok(mut o) = output.lock() {
+                for line in lines { let _ = writeln!(o, "{}", line); }
+                let _ = o.flush();
+            }
+
+            return (backend, best);
+        }));
+    }
+}
+
+/// Get the result to claim for a finished game, e.g. "1-0 {White mates}".
+fn result(status: GameStatus) -> Option<String> {
+    return match status {
+        GameStatus::Ongoing | GameStatus::Check => { None }
+        GameStatus::Checkmate(Color::White) => { Some("1-0 {White mates}".to_string()) }
+        GameStatus::Checkmate(Color::Black) => { Some("0-1 {Black mates}".to_string()) }
+        GameStatus::Stalemate => { Some("1/2-1/2 {Stalemate}".to_string()) }
+        GameStatus::Draw(DrawReason::InsufficientMaterial) => { Some("1/2-1/2 {Insufficient material}".to_string()) }
+        GameStatus::Draw(DrawReason::Agreement) => { Some("1/2-1/2 {Draw agreed}".to_string()) }
+        GameStatus::Draw(DrawReason::Timeout) => { Some("1/2-1/2 {Time forfeit with insufficient mating material}".to_string()) }
+        GameStatus::Timeout(Color::White) => { Some("1-0 {White wins on time}".to_string()) }
+        GameStatus::Timeout(Color::Black) => { Some("0-1 {Black wins on time}".to_string()) }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::testing::{First, Shared};
+
+    fn session(input: &str) -> Vec<String> {
+        let output = Shared::default();
+        XBoard::new(First).run(input.as_bytes(), output.clone()).unwrap();
+        return output.lines();
+    }
+
+    #[test]
+    fn handshake_and_moves() {
+        assert_eq!(session("xboard\nprotover 2\n")[1..], ["feature done=1"]);
+        // The engine plays black after "new" and answers as soon as white has moved.
+        assert_eq!(session("new\nusermove e2e4\nping 1\n"), ["move b8a6", "pong 1"]);
+        assert_eq!(session("new\nforce\nusermove e2e4\nusermove e7e5\ngo\n"), ["move a2a4"]);
+        assert_eq!(session("new\nforce\nusermove e2e4\nundo\nusermove e2e5\n"), ["Illegal move: e2e5"]);
+        assert_eq!(session("new\nfoo\n"), ["Error (unknown command): foo"]);
+    }
+
+    #[test]
+    fn positions_and_results() {
+        assert_eq!(session("setboard 7k/1P6/8/8/8/8/8/K7 w - - 0 1\ngo\n"), ["move b7b8q"]);
+        assert_eq!(session("setboard 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1\ngo\n"), ["1-0 {White mates}"]);
+        assert_eq!(session("setboard 8/8/8/8\n"), ["tellusererror Illegal position"]);
+    }
+
+    #[test]
+    fn time_controls() {
+        let mut xboard = XBoard::new(First);
+        assert!(xboard.set_level(&["40", "5", "0"]));
+        assert!(xboard.set_level(&["0", "2:30", "0.5"]));
+        assert!(!xboard.set_level(&["0", "2:x", "1"]));
+        assert_eq!((xboard.moves_per_session, xboard.limits.increment[0]), (None, Duration::from_millis(500)));
+    }
+}